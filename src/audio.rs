@@ -0,0 +1,195 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{audio::Volume, platform::collections::HashMap, prelude::*};
+
+use crate::{
+    AssetPacks, CurrentMap, GameState, MapManager, SceneManager, ScenePlayer, StateManager,
+};
+
+const MUSIC_VOLUME: f32 = 0.5;
+const CROSSFADE_SECONDS: f32 = 1.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Fade {
+    In,
+    Out,
+}
+
+#[derive(Component, Debug)]
+pub struct MusicTrack {
+    name: String,
+    fade: Fade,
+}
+
+/// Music tracks that have been started, keyed by track name. Tracks that fade
+/// out are paused rather than despawned so they can resume where they left off.
+#[derive(Resource, Debug, Default)]
+pub struct Music {
+    current: Option<String>,
+    tracks: HashMap<String, Entity>,
+}
+
+impl Music {
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+}
+
+fn audio_path(folder: &str, name: &str) -> PathBuf {
+    let path = Path::new(folder).join(name);
+    if path.extension().is_some() {
+        path
+    } else {
+        path.with_extension("ogg")
+    }
+}
+
+/// Resolves a track name like `dusk_at_the_market` to `music/dusk_at_the_market.ogg`.
+pub fn music_track_path(name: &str) -> PathBuf {
+    audio_path("music", name)
+}
+
+/// Resolves a sound name like `punch` to `sounds/punch.ogg`.
+pub fn sound_effect_path(name: &str) -> PathBuf {
+    audio_path("sounds", name)
+}
+
+/// Plays a one-shot sound effect, despawning it once it finishes.
+pub fn play_sound_effect(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    packs: &AssetPacks,
+    name: &str,
+    volume: Option<f32>,
+) {
+    let path = sound_effect_path(name);
+    let Some(asset_path) = packs.find(&path) else {
+        warn!("no sound named {name:?} at: {path:?}");
+        return;
+    };
+    let volume = volume.map(Volume::Linear).unwrap_or_default();
+    commands.spawn((
+        AudioPlayer::new(asset_server.load(asset_path)),
+        PlaybackSettings::DESPAWN.with_volume(volume),
+    ));
+}
+
+/// The track that should be playing for the current state stack.
+///
+/// Walks the stack from the top, so the previous state's track resumes when a
+/// state is popped. Scenes without music keep whatever is playing beneath them.
+pub fn desired_music_track(
+    state_manager: &StateManager,
+    scene_player: Option<&ScenePlayer>,
+    scene_manager: &SceneManager,
+    current_map: Option<&CurrentMap>,
+    map_manager: &MapManager,
+) -> Option<String> {
+    for state in state_manager.0.iter().rev() {
+        match state {
+            GameState::Dialogue => {
+                let scene_music = scene_player
+                    .and_then(|scene_player| scene_manager.get_scene(scene_player.scene()))
+                    .and_then(|scene| scene.music());
+                if let Some(music) = scene_music {
+                    return Some(music.to_string());
+                }
+            }
+            GameState::Map => {
+                return current_map
+                    .and_then(|current_map| map_manager.get_map(&current_map.0))
+                    .and_then(|map| map.music())
+                    .map(str::to_string);
+            }
+            GameState::Battle => {}
+        }
+    }
+    None
+}
+
+pub fn update_music(
+    mut commands: Commands,
+    mut music: ResMut<Music>,
+    asset_server: Res<AssetServer>,
+    state_manager: Res<StateManager>,
+    scene_player: Option<Res<ScenePlayer>>,
+    scene_manager: Res<SceneManager>,
+    current_map: Option<Res<CurrentMap>>,
+    map_manager: Res<MapManager>,
+    packs: Res<AssetPacks>,
+    mut track_query: Query<&mut MusicTrack>,
+) {
+    let desired = desired_music_track(
+        &state_manager,
+        scene_player.as_deref(),
+        &scene_manager,
+        current_map.as_deref(),
+        &map_manager,
+    );
+    if desired == music.current {
+        return;
+    }
+    info!("switching music from {:?} to {desired:?}", music.current);
+
+    if let Some(current) = music.current.take()
+        && let Some(mut track) = music
+            .tracks
+            .get(&current)
+            .and_then(|entity| track_query.get_mut(*entity).ok())
+    {
+        track.fade = Fade::Out;
+    }
+
+    if let Some(name) = &desired {
+        if let Some(&entity) = music.tracks.get(name) {
+            if let Ok(mut track) = track_query.get_mut(entity) {
+                track.fade = Fade::In;
+            }
+        } else {
+            let path = music_track_path(name);
+            if let Some(asset_path) = packs.find(&path) {
+                let entity = commands
+                    .spawn((
+                        AudioPlayer::new(asset_server.load(asset_path)),
+                        PlaybackSettings::LOOP.with_volume(Volume::SILENT),
+                        MusicTrack {
+                            name: name.clone(),
+                            fade: Fade::In,
+                        },
+                    ))
+                    .id();
+                music.tracks.insert(name.clone(), entity);
+            } else {
+                warn!("no music track named {name:?} at: {path:?}");
+            }
+        }
+    }
+
+    music.current = desired;
+}
+
+/// Crossfades tracks. Without an audio device no [`AudioSink`] is ever
+/// created, so this quietly does nothing.
+pub fn fade_music(time: Res<Time>, mut track_query: Query<(&MusicTrack, &mut AudioSink)>) {
+    let step = time.delta_secs() * MUSIC_VOLUME / CROSSFADE_SECONDS;
+    for (track, mut sink) in track_query.iter_mut() {
+        let volume = sink.volume().to_linear();
+        match track.fade {
+            Fade::In => {
+                if sink.is_paused() {
+                    info!("resuming music track: {:?}", track.name);
+                    sink.play();
+                }
+                sink.set_volume(Volume::Linear((volume + step).min(MUSIC_VOLUME)));
+            }
+            Fade::Out if !sink.is_paused() => {
+                let volume = (volume - step).max(0.0);
+                sink.set_volume(Volume::Linear(volume));
+                if volume == 0.0 {
+                    sink.pause();
+                }
+            }
+            Fade::Out => {}
+        }
+    }
+}
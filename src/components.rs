@@ -6,11 +6,15 @@ use bevy::{
     prelude::Component,
     reflect::Reflect,
 };
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
-use crate::{ItemInstance, ItemInstanceId, ItemKind, ItemManager, utils::*};
+use crate::{
+    ExperienceCurve, ItemId, ItemInstance, ItemInstanceId, ItemKind, ItemManager, VendorId,
+    utils::*,
+};
 
-#[derive(Clone, Copy, Reflect, Debug, Hash, PartialEq, Eq, Deserialize)]
+#[derive(Clone, Copy, Reflect, Debug, Hash, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ArmorSlot {
     Head,
@@ -19,7 +23,7 @@ pub enum ArmorSlot {
     Hands,
 }
 
-#[derive(Default, Debug, Hash, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Default, Debug, Hash, PartialEq, Eq)]
 pub struct ArmorSlots {
     head: Option<ItemInstanceId>,
     body: Option<ItemInstanceId>,
@@ -46,6 +50,25 @@ impl ArmorSlots {
         }
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = &ItemInstanceId> {
+        self.head
+            .iter()
+            .chain(self.body.iter())
+            .chain(self.hands.iter())
+            .chain(self.feet.iter())
+    }
+
+    pub fn find(&self, item: &ItemInstanceId) -> Option<ArmorSlot> {
+        [
+            ArmorSlot::Head,
+            ArmorSlot::Body,
+            ArmorSlot::Feet,
+            ArmorSlot::Hands,
+        ]
+        .into_iter()
+        .find(|slot| self.get(*slot) == Some(item))
+    }
+
     pub fn set(&mut self, slot: ArmorSlot, item: ItemInstanceId) -> Option<ItemInstanceId> {
         self.get_mut(slot).replace(item)
     }
@@ -54,8 +77,81 @@ impl ArmorSlots {
         self.get_mut(slot).take()
     }
 
-    pub fn damage_restistance(&self) {
-        todo!()
+    pub fn damage_resistance(&self, inventory: &Inventory, item_manager: &ItemManager) -> u32 {
+        self.iter()
+            .filter_map(|instance_id| {
+                get_item_instance(Some(*instance_id), inventory, item_manager)
+            })
+            .map(|(instance, item)| instance.effective_defense(item))
+            .sum()
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, Hash, PartialEq, Eq, derive_more::From)]
+#[serde(rename_all = "lowercase")]
+pub enum Skill {
+    Strength,
+    Perception,
+    Endurance,
+    Charisma,
+    Intelligence,
+    Agility,
+    Luck,
+}
+
+impl Skill {
+    pub const ALL: [Skill; 7] = [
+        Skill::Strength,
+        Skill::Perception,
+        Skill::Endurance,
+        Skill::Charisma,
+        Skill::Intelligence,
+        Skill::Agility,
+        Skill::Luck,
+    ];
+}
+
+impl std::fmt::Display for Skill {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone, Copy, Hash, PartialEq, Eq)]
+#[serde(rename_all = "lowercase", default, deny_unknown_fields)]
+pub struct Stats {
+    pub strength: u32,
+    pub perception: u32,
+    pub endurance: u32,
+    pub charisma: u32,
+    pub intelligence: u32,
+    pub agility: u32,
+    pub luck: u32,
+}
+
+impl Stats {
+    pub fn get(&self, skill: Skill) -> u32 {
+        match skill {
+            Skill::Strength => self.strength,
+            Skill::Perception => self.perception,
+            Skill::Endurance => self.endurance,
+            Skill::Charisma => self.charisma,
+            Skill::Intelligence => self.intelligence,
+            Skill::Agility => self.agility,
+            Skill::Luck => self.luck,
+        }
+    }
+
+    pub fn get_mut(&mut self, skill: Skill) -> &mut u32 {
+        match skill {
+            Skill::Strength => &mut self.strength,
+            Skill::Perception => &mut self.perception,
+            Skill::Endurance => &mut self.endurance,
+            Skill::Charisma => &mut self.charisma,
+            Skill::Intelligence => &mut self.intelligence,
+            Skill::Agility => &mut self.agility,
+            Skill::Luck => &mut self.luck,
+        }
     }
 }
 
@@ -69,7 +165,15 @@ pub struct Npc {
     pub voice: NpcVoice,
 }
 
-#[derive(Component, Debug, Hash, Clone, PartialEq, Eq, Deserialize)]
+/// A vendor spawned by a scene. Its stock is the [`Inventory`] next to it.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct Shopkeeper {
+    pub id: VendorId,
+    pub name: String,
+    pub gold: u32,
+}
+
+#[derive(Component, Debug, Hash, Clone, PartialEq, Eq, Deserialize, JsonSchema)]
 pub struct NpcImage(pub PathBuf);
 impl Default for NpcImage {
     fn default() -> Self {
@@ -77,7 +181,7 @@ impl Default for NpcImage {
     }
 }
 
-#[derive(Component, Debug, Hash, Clone, PartialEq, Eq, Deserialize)]
+#[derive(Component, Debug, Hash, Clone, PartialEq, Eq, Deserialize, JsonSchema)]
 pub struct NpcVoice(pub String);
 impl Default for NpcVoice {
     fn default() -> Self {
@@ -86,25 +190,46 @@ impl Default for NpcVoice {
 }
 
 #[derive(
-    Debug, derive_more::Display, PartialEq, Eq, Deserialize, Clone, Hash, derive_more::From,
+    Debug,
+    derive_more::Display,
+    PartialEq,
+    Eq,
+    Deserialize,
+    JsonSchema,
+    Clone,
+    Hash,
+    derive_more::From,
 )]
 pub struct NpcId(pub String);
 
-#[derive(Component, Debug)]
+/// A character's state, which saves keep in full.
+#[derive(Component, Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
 pub struct RpgEntity {
     name: String,
     damage: f32,
+    max_health: f32,
+    level: u32,
+    experience: u32,
+    stats: Stats,
+    unspent_points: u32,
     armor: ArmorSlots,
     weapon: Option<ItemInstanceId>,
     shield: Option<ItemInstanceId>,
+    #[schemars(with = "Vec<ItemInstance>")]
     pub inventory: Inventory,
 }
 
 impl RpgEntity {
-    pub fn new(name: Option<String>) -> Self {
+    pub fn new(name: Option<String>, curve: &ExperienceCurve) -> Self {
         Self {
             name: name.unwrap_or_else(|| "?".to_string()),
             damage: 0.0,
+            max_health: curve.max_health(1),
+            level: 1,
+            experience: 0,
+            stats: Stats::default(),
+            unspent_points: 0,
             armor: ArmorSlots::default(),
             weapon: None,
             shield: None,
@@ -120,6 +245,59 @@ impl RpgEntity {
         self.name = name
     }
 
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    /// Sets the level directly, without granting stat points.
+    pub fn set_level(&mut self, level: u32, curve: &ExperienceCurve) {
+        self.level = level;
+        self.experience = 0;
+        self.max_health = curve.max_health(level);
+    }
+
+    pub fn experience(&self) -> u32 {
+        self.experience
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn set_stats(&mut self, stats: Stats) {
+        self.stats = stats
+    }
+
+    pub fn unspent_points(&self) -> u32 {
+        self.unspent_points
+    }
+
+    /// Adds experience, levelling up as many times as it allows.
+    ///
+    /// Returns the number of levels gained.
+    pub fn gain_experience(&mut self, amount: u32, curve: &ExperienceCurve) -> u32 {
+        self.experience = self.experience.saturating_add(amount);
+        let mut levels_gained = 0;
+        while self.experience >= curve.experience_to_next(self.level) {
+            self.experience -= curve.experience_to_next(self.level);
+            self.level += 1;
+            self.max_health = curve.max_health(self.level);
+            self.unspent_points += curve.points_per_level;
+            levels_gained += 1;
+        }
+        levels_gained
+    }
+
+    /// Spends an unspent stat point on `skill`.
+    pub fn allocate_point(&mut self, skill: Skill) -> bool {
+        if self.unspent_points == 0 {
+            return false;
+        }
+        self.unspent_points -= 1;
+        *self.stats.get_mut(skill) += 1;
+        true
+    }
+
     pub fn equip(&mut self, instance_id: ItemInstanceId) -> bool {
         let Some(item_instance) = self.inventory.get(&instance_id) else {
             warn!("could not equip: {instance_id:?}");
@@ -131,10 +309,10 @@ impl RpgEntity {
                 self.armor.set(*slot, instance_id);
             }
             ItemKind::Weapon => {
-                self.weapon.insert(instance_id);
+                self.weapon = Some(instance_id);
             }
             ItemKind::Shield => {
-                self.shield.insert(instance_id);
+                self.shield = Some(instance_id);
             }
             ItemKind::Food | ItemKind::Potion => {
                 return false;
@@ -145,10 +323,37 @@ impl RpgEntity {
         true
     }
 
-    pub fn apply_damage(&mut self, damage: f32) -> DamageResult {
-        // TODO: apply damage resistance
-        let reduced_damage = damage;
+    pub fn unequip(&mut self, instance_id: &ItemInstanceId) -> bool {
+        if self.weapon.as_ref() == Some(instance_id) {
+            self.weapon = None;
+        } else if self.shield.as_ref() == Some(instance_id) {
+            self.shield = None;
+        } else if let Some(slot) = self.armor.find(instance_id) {
+            self.armor.remove(slot);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Removes up to `count` items with the given ID, preferring items that
+    /// are not equipped. Anything removed is unequipped.
+    pub fn remove_items(&mut self, item_id: &ItemId, count: u32) -> Vec<ItemInstance> {
+        let equipped: Vec<ItemInstanceId> = self.equipped().copied().collect();
+        let removed = self
+            .inventory
+            .remove_items_where(item_id, count, |instance_id| equipped.contains(instance_id));
+        for instance in removed.iter() {
+            self.unequip(&instance.instance_id());
+        }
+        removed
+    }
+
+    pub fn apply_damage(&mut self, damage: f32, item_manager: &ItemManager) -> DamageResult {
+        let resistance = self.damage_resistance(item_manager) as f32;
+        let reduced_damage = damage * 100.0 / (100.0 + resistance);
         self.damage += reduced_damage;
+        self.wear_armor();
 
         DamageResult {
             reduced_damage,
@@ -162,7 +367,7 @@ impl RpgEntity {
     }
 
     pub fn max_health(&self) -> f32 {
-        20.0 // TODO
+        self.max_health
     }
 
     pub fn health(&self) -> f32 {
@@ -171,10 +376,45 @@ impl RpgEntity {
 
     pub fn attack_damage(&self, item_manager: &ItemManager) -> f32 {
         // TODO: adjust based on character stats
-        if let Some(weapon) = get_weapon(self.weapon, &self.inventory, item_manager) {
-            weapon.damage() as f32
-        } else {
-            1.0
+        match get_item_instance(self.weapon, &self.inventory, item_manager) {
+            Some((instance, item)) if item.is_weapon() && !instance.is_broken() => {
+                instance.effective_damage(item) as f32
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Total defense of equipped armor and shield, including enchantments.
+    pub fn damage_resistance(&self, item_manager: &ItemManager) -> u32 {
+        let shield = get_item_instance(self.shield, &self.inventory, item_manager)
+            .map(|(instance, item)| instance.effective_defense(item))
+            .unwrap_or(0);
+        self.armor.damage_resistance(&self.inventory, item_manager) + shield
+    }
+
+    pub fn wear_weapon(&mut self) {
+        if let Some(weapon) = self.weapon {
+            self.wear(&weapon);
+        }
+    }
+
+    pub fn wear_armor(&mut self) {
+        let worn: Vec<ItemInstanceId> = self
+            .armor
+            .iter()
+            .chain(self.shield.iter())
+            .copied()
+            .collect();
+        for instance_id in worn {
+            self.wear(&instance_id);
+        }
+    }
+
+    fn wear(&mut self, instance_id: &ItemInstanceId) {
+        if let Some(instance) = self.inventory.get_mut(instance_id)
+            && instance.wear(1)
+        {
+            info!("{:?}'s {:?} broke", self.name, instance.item_id());
         }
     }
 
@@ -195,10 +435,7 @@ impl RpgEntity {
         self.weapon
             .iter()
             .chain(self.shield.iter())
-            .chain(self.armor.head.iter())
-            .chain(self.armor.body.iter())
-            .chain(self.armor.hands.iter())
-            .chain(self.armor.feet.iter())
+            .chain(self.armor.iter())
     }
 
     pub fn is_equipped(&self, instance_id: &ItemInstanceId) -> bool {
@@ -213,21 +450,132 @@ impl std::fmt::Display for RpgEntity {
     }
 }
 
-#[derive(Component, Default, Debug)]
+/// Saved as a list of item instances, modifiers included.
+#[derive(Component, Serialize, Deserialize, Clone, Default, Debug, PartialEq, Eq)]
+#[serde(from = "Vec<ItemInstance>", into = "Vec<ItemInstance>")]
 pub struct Inventory {
     pub(crate) items: HashMap<ItemInstanceId, ItemInstance>,
 }
 
+impl From<Vec<ItemInstance>> for Inventory {
+    fn from(instances: Vec<ItemInstance>) -> Self {
+        // kept as they are, so equipped instance IDs still point at them
+        let items = instances
+            .into_iter()
+            .map(|instance| (instance.instance_id(), instance))
+            .collect();
+        Self { items }
+    }
+}
+
+impl From<Inventory> for Vec<ItemInstance> {
+    fn from(inventory: Inventory) -> Self {
+        let mut instances: Vec<ItemInstance> = inventory.items.into_values().collect();
+        // the same inventory always saves the same way
+        instances.sort_by_key(|instance| {
+            (
+                instance.item_id().clone(),
+                instance.instance_id().to_string(),
+            )
+        });
+        instances
+    }
+}
+
 impl Inventory {
     pub fn get(&self, id: &ItemInstanceId) -> Option<&ItemInstance> {
         self.items.get(id)
     }
 
+    pub fn get_mut(&mut self, id: &ItemInstanceId) -> Option<&mut ItemInstance> {
+        self.items.get_mut(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ItemInstance> {
+        self.items.values()
+    }
+
+    /// Inserts an item, merging it into an existing stack when possible.
+    ///
+    /// Returns the ID of the instance that now holds the item.
     pub fn insert(&mut self, instance: ItemInstance) -> ItemInstanceId {
+        if let Some(stack) = self
+            .items
+            .values_mut()
+            .find(|stack| stack.stacks_with(&instance))
+        {
+            stack.merge(instance);
+            return stack.instance_id();
+        }
         let instance_id = instance.instance_id();
         self.items.insert(instance_id, instance);
         instance_id
     }
+
+    pub fn remove(&mut self, id: &ItemInstanceId) -> Option<ItemInstance> {
+        self.items.remove(id)
+    }
+
+    /// Takes `count` items out of a stack. The whole instance is removed if
+    /// `count` covers the entire stack.
+    pub fn split(&mut self, id: &ItemInstanceId, count: u32) -> Option<ItemInstance> {
+        let stack = self.items.get_mut(id)?;
+        if count == 0 {
+            return None;
+        }
+        if count >= stack.quantity() {
+            return self.items.remove(id);
+        }
+        stack.split_off(count)
+    }
+
+    /// Total quantity of the given item across all instances and stacks.
+    pub fn count(&self, item_id: &ItemId) -> u32 {
+        self.items
+            .values()
+            .filter(|instance| instance.item_id() == item_id)
+            .map(ItemInstance::quantity)
+            .sum()
+    }
+
+    pub fn has_item(&self, item_id: &ItemId, count: u32) -> bool {
+        self.count(item_id) >= count
+    }
+
+    /// Removes up to `count` items with the given ID and returns them.
+    pub fn remove_items(&mut self, item_id: &ItemId, count: u32) -> Vec<ItemInstance> {
+        self.remove_items_where(item_id, count, |_| false)
+    }
+
+    /// Like [`Inventory::remove_items`], but takes from instances for which
+    /// `keep_last` returns `true` only after all others are used up.
+    pub(crate) fn remove_items_where(
+        &mut self,
+        item_id: &ItemId,
+        count: u32,
+        keep_last: impl Fn(&ItemInstanceId) -> bool,
+    ) -> Vec<ItemInstance> {
+        let mut candidates: Vec<ItemInstanceId> = self
+            .items
+            .values()
+            .filter(|instance| instance.item_id() == item_id)
+            .map(ItemInstance::instance_id)
+            .collect();
+        candidates.sort_by_key(|instance_id| keep_last(instance_id));
+
+        let mut removed = Vec::new();
+        let mut remaining = count;
+        for instance_id in candidates {
+            if remaining == 0 {
+                break;
+            }
+            if let Some(instance) = self.split(&instance_id, remaining) {
+                remaining -= instance.quantity();
+                removed.push(instance);
+            }
+        }
+        removed
+    }
 }
 
 pub enum LifeStatus {
@@ -0,0 +1,220 @@
+use std::{collections::BTreeSet, fmt, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{SceneBookmark, SceneId, SceneManager, ScenePlayer, SceneSectionId};
+
+/// Sections, lines and responses that have been reached.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct DialogueCoverage {
+    reached: BTreeSet<SceneBookmark>,
+}
+
+impl DialogueCoverage {
+    /// Loads coverage saved by [`DialogueCoverage::save`]. A missing file is
+    /// treated as nothing having been reached yet.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Adds this coverage to whatever is already saved at `path`, so coverage
+    /// builds up across play sessions and test runs.
+    pub fn merge_into_file(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let mut saved = Self::load(&path)?;
+        saved.merge(self);
+        saved.save(path)
+    }
+
+    pub fn merge(&mut self, other: &DialogueCoverage) {
+        self.reached.extend(other.reached.iter().cloned());
+    }
+
+    pub fn record(&mut self, scene_player: &ScenePlayer) {
+        self.reached.extend(scene_player.visited().iter().cloned());
+    }
+
+    pub fn contains(&self, bookmark: &SceneBookmark) -> bool {
+        self.reached.contains(bookmark)
+    }
+}
+
+pub fn record_dialogue_coverage(
+    mut coverage: ResMut<DialogueCoverage>,
+    scene_player: Option<Res<ScenePlayer>>,
+) {
+    if let Some(scene_player) = scene_player.filter(|scene_player| scene_player.is_changed()) {
+        coverage.record(&scene_player);
+    }
+}
+
+/// How much of a scene has been reached, and what hasn't.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SceneCoverage {
+    pub scene: SceneId,
+    /// `(reached, total)` pairs.
+    pub sections: (usize, usize),
+    pub lines: (usize, usize),
+    pub responses: (usize, usize),
+    pub unreached_sections: Vec<SceneSectionId>,
+    /// Section, index and text of each response nobody has chosen.
+    pub unreached_responses: Vec<(SceneSectionId, usize, String)>,
+}
+
+impl SceneCoverage {
+    pub fn is_complete(&self) -> bool {
+        self.sections.0 == self.sections.1
+            && self.lines.0 == self.lines.1
+            && self.responses.0 == self.responses.1
+    }
+}
+
+impl fmt::Display for SceneCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {}/{} sections, {}/{} lines, {}/{} responses",
+            self.scene,
+            self.sections.0,
+            self.sections.1,
+            self.lines.0,
+            self.lines.1,
+            self.responses.0,
+            self.responses.1,
+        )?;
+        for section in &self.unreached_sections {
+            writeln!(f, "    unreached section `{section}`")?;
+        }
+        for (section, i, text) in &self.unreached_responses {
+            writeln!(f, "    unreached response `{section}` #{i}: {text:?}")?;
+        }
+        Ok(())
+    }
+}
+
+impl SceneManager {
+    /// Coverage of every scene, sorted by scene ID.
+    pub fn coverage(&self, coverage: &DialogueCoverage) -> Vec<SceneCoverage> {
+        let mut scenes: Vec<_> = self.scenes.values().collect();
+        scenes.sort_by_key(|scene| scene.id().0.clone());
+        scenes
+            .into_iter()
+            .map(|scene| {
+                let mut sections: Vec<_> = scene.dialogue.iter().collect();
+                sections.sort_by_key(|(section, _)| section.0.clone());
+
+                let mut report = SceneCoverage {
+                    scene: scene.id().clone(),
+                    sections: (0, 0),
+                    lines: (0, 0),
+                    responses: (0, 0),
+                    unreached_sections: Vec::new(),
+                    unreached_responses: Vec::new(),
+                };
+                let reached = |section, line, response| {
+                    coverage.contains(&SceneBookmark::new(
+                        scene.id(),
+                        Some(section),
+                        line,
+                        response,
+                    ))
+                };
+                for (section, dialogue) in sections {
+                    report.sections.1 += 1;
+                    if reached(section, None, None) {
+                        report.sections.0 += 1;
+                    } else {
+                        report.unreached_sections.push(section.clone());
+                    }
+                    for i in 0..dialogue.lines.len() {
+                        report.lines.1 += 1;
+                        if reached(section, Some(i), None) {
+                            report.lines.0 += 1;
+                        }
+                    }
+                    for (i, response) in dialogue.responses.iter().enumerate() {
+                        report.responses.1 += 1;
+                        if reached(section, None, Some(i)) {
+                            report.responses.0 += 1;
+                        } else {
+                            report.unreached_responses.push((
+                                section.clone(),
+                                i,
+                                response.text.clone(),
+                            ));
+                        }
+                    }
+                }
+                report
+            })
+            .collect()
+    }
+}
+
+/// Lists what the coverage saved at `coverage_path` hasn't reached in the
+/// scenes under `assets_path`.
+pub fn coverage_report(assets_path: &Path, coverage_path: &Path) -> anyhow::Result<String> {
+    let scene_manager = SceneManager::new().with_load_folder(assets_path.join("scenes"))?;
+    let coverage = DialogueCoverage::load(coverage_path)?;
+    let scenes = scene_manager.coverage(&coverage);
+
+    let mut report = String::new();
+    for scene in scenes.iter().filter(|scene| !scene.is_complete()) {
+        report.push_str(&scene.to_string());
+    }
+    let complete = scenes.iter().filter(|scene| scene.is_complete()).count();
+    report.push_str(&format!(
+        "{complete}/{} scenes fully covered\n",
+        scenes.len()
+    ));
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        DialogueCoverage, ItemManager, SceneId, SceneManager, SceneRunner, SceneSectionId,
+    };
+
+    #[test]
+    fn reports_unreached_sections_and_responses() {
+        let scene_manager = SceneManager::new()
+            .with_load_scene(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/assets/scenes/test.scene.json"
+            ))
+            .unwrap();
+        let mut runner = SceneRunner::with_managers(ItemManager::new(), scene_manager.clone(), 0);
+        runner.play(&SceneId::new("test")).unwrap();
+        runner.run_script("select 0, continue").unwrap();
+
+        let mut coverage = DialogueCoverage::default();
+        coverage.merge(runner.coverage());
+        let report = &scene_manager.coverage(&coverage)[0];
+
+        assert_eq!(report.scene, SceneId::new("test"));
+        assert!(!report.is_complete());
+        assert!(report.responses.0 == 1 && report.responses.1 > 1);
+        assert!(
+            !report
+                .unreached_sections
+                .contains(&SceneSectionId::default())
+        );
+        assert!(
+            !report
+                .unreached_responses
+                .iter()
+                .any(|(section, i, _)| *section == SceneSectionId::default() && *i == 0)
+        );
+    }
+}
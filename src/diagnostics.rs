@@ -0,0 +1,465 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    asset::{AssetLoadFailedEvent, AssetPath},
+    prelude::*,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    AssetPacks, Character, Condition, Dialogue, FIELD_ALIASES, FormatError, Line, MapAction,
+    Response, SkillCheck, Toasts, known_fields, read_data, utils,
+};
+
+type PathError = serde_path_to_error::Error<serde_json::Error>;
+type Check = fn(&Value) -> Result<(), PathError>;
+
+/// Parts of data files that are read again on their own to narrow down an
+/// error, shallowest first. `*` matches any key or index, and `|` separates a
+/// field from its alias. serde can't tell where in a flattened field an error
+/// is, and scenes and maps flatten their definitions at the top level.
+const PARTS: [(&str, Check); 7] = [
+    ("/characters/*", check::<Character>),
+    ("/actions/*", check::<MapAction>),
+    ("/dialogue/*", check::<Dialogue>),
+    ("/dialogue/*/lines/*", check::<Line>),
+    ("/dialogue/*/responses|resp/*", check::<Response>),
+    (
+        "/dialogue/*/responses|resp/*/skill-check",
+        check::<SkillCheck>,
+    ),
+    (
+        "/dialogue/*/responses|resp/*/conditions|cond/*",
+        check::<Condition>,
+    ),
+];
+
+fn check<T: DeserializeOwned>(value: &Value) -> Result<(), PathError> {
+    serde_path_to_error::deserialize::<_, T>(value).map(drop)
+}
+
+/// Why a data file failed to load, and where in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadDiagnostic {
+    pub file: PathBuf,
+    /// Line and column, counting from 1.
+    pub location: Option<(usize, usize)>,
+    /// JSON pointer to the offending value, e.g. `/dialogue/dig/lines/2`.
+    pub pointer: Option<String>,
+    pub message: String,
+    /// A field the file probably meant, when it has one that doesn't exist.
+    pub suggestion: Option<String>,
+}
+
+impl LoadDiagnostic {
+    pub fn new<P: AsRef<Path>>(file: P, error: &anyhow::Error) -> Self {
+        match error.downcast_ref::<FormatError>() {
+            Some(error) => Self::from_format_error(file, error),
+            None => Self {
+                file: file.as_ref().to_path_buf(),
+                location: None,
+                pointer: None,
+                message: error.to_string(),
+                suggestion: None,
+            },
+        }
+    }
+
+    pub fn from_format_error<P: AsRef<Path>>(file: P, error: &FormatError) -> Self {
+        let (pointer, message) = match error {
+            FormatError::Invalid { pointer, error, .. } => {
+                (Some(pointer.clone()), error.to_string())
+            }
+            FormatError::Json(error) => (None, error.to_string()),
+            FormatError::Yaml(error) => (None, error.to_string()),
+            FormatError::Ron(error) => (None, error.code.to_string()),
+            error => (None, error.to_string()),
+        };
+        // the location is kept separately
+        let message = match message.rsplit_once(" at line ") {
+            Some((message, _)) => message.to_string(),
+            None => message,
+        };
+        Self {
+            file: file.as_ref().to_path_buf(),
+            location: error.location(),
+            pointer,
+            suggestion: suggest_field(&message),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for LoadDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some((line, column)) = self.location {
+            write!(f, ":{line}:{column}")?;
+        }
+        match self.pointer.as_deref() {
+            Some("") => write!(f, ": at the top level")?,
+            Some(pointer) => write!(f, ": at `{pointer}`")?,
+            None => {}
+        }
+        write!(f, ": {}", self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean `{suggestion}`?)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Picks the field or variant closest to the unknown one in a serde error
+/// like "unknown field `tetx`, expected one of `text`, `link`". Aliases of
+/// the expected fields count too, so `lnk-fial` suggests `lnk-fail`. Types
+/// with flattened fields don't say what they expect, so any known field will
+/// do for those.
+fn suggest_field(message: &str) -> Option<String> {
+    let rest = message
+        .strip_prefix("unknown field `")
+        .or_else(|| message.strip_prefix("unknown variant `"))?;
+    let (unknown, expected) = rest.split_once('`')?;
+    let mut candidates: Vec<String> = expected
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(str::to_string)
+        .collect();
+    if candidates.is_empty() {
+        candidates.extend(known_fields());
+    }
+    for (field, alias) in FIELD_ALIASES {
+        if candidates.iter().any(|c| c == field) && !candidates.iter().any(|c| c == alias) {
+            candidates.push(alias.to_string());
+        }
+    }
+
+    // `link_fail` and `Link-Fail` are the same mistake as a typo
+    let normalize = |name: &str| name.to_lowercase().replace('_', "-");
+    let unknown = normalize(unknown);
+    candidates
+        .into_iter()
+        .map(|candidate| {
+            (
+                utils::edit_distance(&unknown, &normalize(&candidate)),
+                candidate,
+            )
+        })
+        .filter(|(distance, _)| *distance <= (unknown.len() / 3).max(2))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Turns a path like `dialogue.dig.lines[2]` into the JSON pointer
+/// `/dialogue/dig/lines/2`. Keys serde can't name, like section IDs, end the
+/// pointer there, since everything after them would point somewhere else.
+fn json_pointer(path: &serde_path_to_error::Path) -> String {
+    use serde_path_to_error::Segment;
+
+    path.iter()
+        .map_while(|segment| match segment {
+            Segment::Seq { index } => Some(index.to_string()),
+            Segment::Map { key } => Some(key.replace('~', "~0").replace('/', "~1")),
+            Segment::Enum { variant } => Some(variant.clone()),
+            Segment::Unknown => None,
+        })
+        .map(|segment| format!("/{segment}"))
+        .collect()
+}
+
+/// Every value in `value` that `pattern` from [`PARTS`] matches, with its
+/// JSON pointer.
+fn matches<'a>(value: &'a Value, pattern: &str) -> Vec<(String, &'a Value)> {
+    let mut found = vec![(String::new(), value)];
+    for segment in pattern.split('/').skip(1) {
+        let matches_key = |key: &str| segment == "*" || segment.split('|').any(|s| s == key);
+        found = found
+            .into_iter()
+            .flat_map(|(pointer, value)| {
+                let children: Vec<(String, &Value)> = match value {
+                    Value::Object(object) => object
+                        .iter()
+                        .filter(|(key, _)| matches_key(key))
+                        .map(|(key, value)| (key.replace('~', "~0").replace('/', "~1"), value))
+                        .collect(),
+                    Value::Array(values) if segment == "*" => values
+                        .iter()
+                        .enumerate()
+                        .map(|(i, value)| (i.to_string(), value))
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                children
+                    .into_iter()
+                    .map(move |(key, value)| (format!("{pointer}/{key}"), value))
+            })
+            .collect();
+    }
+    found
+}
+
+/// Finds the smallest part of `value` that an error reading it comes from,
+/// returning a JSON pointer to it and the error reading it on its own gives.
+pub fn narrow_error(value: &Value, error: PathError) -> (String, serde_json::Error) {
+    let mut pointer = json_pointer(error.path());
+    let mut error = error.into_inner();
+    for (pattern, check) in PARTS {
+        for (part_pointer, part) in matches(value, pattern) {
+            let inside = pointer.is_empty()
+                || part_pointer == pointer
+                || part_pointer.starts_with(&format!("{pointer}/"));
+            if !inside {
+                continue;
+            }
+            if let Err(part_error) = check(part) {
+                pointer = part_pointer + &json_pointer(part_error.path());
+                error = part_error.into_inner();
+                break;
+            }
+        }
+    }
+
+    // point at the unknown field itself rather than the object it's in
+    let message = error.to_string();
+    let unknown_field = message
+        .strip_prefix("unknown field `")
+        .and_then(|rest| rest.split_once('`'))
+        .map(|(field, _)| field)
+        .filter(|field| {
+            value
+                .pointer(&pointer)
+                .and_then(Value::as_object)
+                .is_some_and(|object| object.contains_key(*field))
+        });
+    if let Some(field) = unknown_field {
+        pointer = format!("{pointer}/{}", field.replace('~', "~0").replace('/', "~1"));
+    }
+    (pointer, error)
+}
+
+/// Reads just enough of some JSON text to find things in it.
+struct Scanner<'a> {
+    json: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.json.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Option<()> {
+        self.skip_whitespace();
+        (self.peek()? == c).then(|| self.position += 1)
+    }
+
+    /// Reads a string, leaving any escapes in it as they are.
+    fn string(&mut self) -> Option<&'a str> {
+        self.expect(b'"')?;
+        let start = self.position;
+        loop {
+            match self.peek()? {
+                b'\\' => self.position += 2,
+                b'"' => break,
+                _ => self.position += 1,
+            }
+        }
+        self.position += 1;
+        std::str::from_utf8(&self.json[start..self.position - 1]).ok()
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        self.skip_whitespace();
+        match self.peek()? {
+            b'"' => self.string().map(drop),
+            b'{' | b'[' => {
+                let mut depth = 0;
+                loop {
+                    match self.peek()? {
+                        b'"' => {
+                            self.string()?;
+                            continue;
+                        }
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => depth -= 1,
+                        _ => {}
+                    }
+                    self.position += 1;
+                    if depth == 0 {
+                        return Some(());
+                    }
+                }
+            }
+            _ => {
+                while self.peek().is_some_and(|c| !b",}]".contains(&c)) {
+                    self.position += 1;
+                }
+                Some(())
+            }
+        }
+    }
+
+    /// Moves to the value at `segment` of the object or array it's at,
+    /// returning where its key starts if it has one.
+    fn enter(&mut self, segment: &str) -> Option<Option<usize>> {
+        self.skip_whitespace();
+        match self.peek()? {
+            b'{' => {
+                self.position += 1;
+                let segment = segment.replace("~1", "/").replace("~0", "~");
+                loop {
+                    self.skip_whitespace();
+                    let key_start = self.position;
+                    let key = self.string()?;
+                    let found = key == segment;
+                    self.expect(b':')?;
+                    if found {
+                        return Some(Some(key_start));
+                    }
+                    self.skip_value()?;
+                    self.expect(b',')?;
+                }
+            }
+            b'[' => {
+                self.position += 1;
+                for _ in 0..segment.parse::<usize>().ok()? {
+                    self.skip_value()?;
+                    self.expect(b',')?;
+                }
+                Some(None)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The line and column, counting from 1, of what `pointer` points to in the
+/// JSON text `json`. Values in objects are found by their key.
+pub fn locate(json: &str, pointer: &str) -> Option<(usize, usize)> {
+    let mut scanner = Scanner {
+        json: json.as_bytes(),
+        position: 0,
+    };
+    let mut key_start = None;
+    for segment in pointer.split('/').skip(1) {
+        key_start = scanner.enter(segment)?;
+    }
+    scanner.skip_whitespace();
+    let position = key_start.unwrap_or(scanner.position);
+
+    let before = &json[..position];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rfind('\n')
+        .map_or(before, |newline| &before[newline + 1..])
+        .chars()
+        .count()
+        + 1;
+    Some((line, column))
+}
+
+/// Every file that failed to load, for the debug panel.
+#[derive(Resource, Debug, Default)]
+pub struct LoadDiagnostics(pub Vec<LoadDiagnostic>);
+
+impl LoadDiagnostics {
+    /// Records `diagnostic`, replacing any earlier one for the same file.
+    pub fn push(&mut self, diagnostic: LoadDiagnostic) {
+        self.clear(&diagnostic.file);
+        self.0.push(diagnostic);
+    }
+
+    pub fn clear(&mut self, file: &Path) {
+        self.0.retain(|diagnostic| diagnostic.file != file);
+    }
+}
+
+/// Where the file at `path` is on disk.
+fn disk_path(packs: &AssetPacks, path: &AssetPath) -> PathBuf {
+    let pack = packs.priority(path.source());
+    let pack_path = packs
+        .iter()
+        .nth(pack)
+        .map(|pack| pack.path.as_path())
+        .unwrap_or(Path::new(""));
+    pack_path.join(path.path())
+}
+
+/// Logs and shows every `A` that failed to load. The asset server only keeps
+/// the error's text, so the file is read again to find where the problem is.
+/// When a file is reloaded and fails, the asset server keeps the previous
+/// version, so the game carries on with that.
+pub fn report_load_failures<A: Asset + DeserializeOwned>(
+    mut load_failed_events: EventReader<AssetLoadFailedEvent<A>>,
+    mut diagnostics: ResMut<LoadDiagnostics>,
+    mut toasts: ResMut<Toasts>,
+    packs: Res<AssetPacks>,
+) {
+    for AssetLoadFailedEvent { path, error, .. } in load_failed_events.read() {
+        let file = disk_path(&packs, path);
+        let diagnostic = match read_data::<A, _>(&file) {
+            Err(e) => LoadDiagnostic::from_format_error(&file, &e),
+            // fixed since, or never a problem with the file itself
+            Ok(_) => LoadDiagnostic::new(&file, &anyhow::anyhow!("{error}")),
+        };
+        error!("failed to load {diagnostic}");
+        toasts.push(format!("failed to load {diagnostic}"));
+        diagnostics.push(diagnostic);
+    }
+}
+
+/// Forgets the problems of files that have since loaded.
+pub fn clear_load_diagnostics<A: Asset>(
+    mut asset_events: EventReader<AssetEvent<A>>,
+    mut diagnostics: ResMut<LoadDiagnostics>,
+    asset_server: Res<AssetServer>,
+    packs: Res<AssetPacks>,
+) {
+    for event in asset_events.read() {
+        let (AssetEvent::Added { id } | AssetEvent::Modified { id }) = event else {
+            continue;
+        };
+        if let Some(path) = asset_server.get_path(*id) {
+            diagnostics.clear(&disk_path(&packs, &path));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{DataFormat, LoadDiagnostic, Scene};
+
+    #[test]
+    fn points_at_unknown_fields() {
+        let json = r#"{
+            "id": "broken",
+            "dialogue": {
+                "start": {
+                    "lines": [],
+                    "responses": [{ "txt": "bye", "lnik": "start" }]
+                }
+            }
+        }"#;
+        let error = DataFormat::Json
+            .parse::<Scene>(json.as_bytes())
+            .unwrap_err();
+        let diagnostic = LoadDiagnostic::from_format_error("broken.scene.json", &error);
+
+        assert_eq!(
+            diagnostic.pointer.as_deref(),
+            Some("/dialogue/start/responses/0/lnik")
+        );
+        assert_eq!(diagnostic.location.map(|(line, _)| line), Some(6));
+        assert_eq!(diagnostic.suggestion.as_deref(), Some("lnk"));
+    }
+}
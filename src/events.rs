@@ -1,8 +1,11 @@
 use bevy::prelude::*;
 
 use crate::{
-    Battle, Character, CharacterUpdate, GameState, ItemManager, SceneBookmark, SceneId,
-    SceneManager, ScenePlayer, StateManager, StaticCommands, components::*, utils,
+    AssetPacks, Battle, Character, CharacterUpdate, CurrentMap, DialogueCoverage, DialogueHistory,
+    ExperienceCurve, GameManifest, GameStart, GameState, ItemId, ItemManager, LootTableId, MapId,
+    MapManager, PlayerPosition, Position, Rng, SceneBookmark, SceneId, SceneManager, ScenePlayer,
+    StateManager, StaticCommands, StaticCommandsWriters, Toasts, Vendor, VendorId, components::*,
+    play_sound_effect, utils,
 };
 
 #[derive(Event)]
@@ -13,19 +16,20 @@ pub struct AttackEvent {
 
 impl AttackEvent {
     pub fn handler(
-        query: Query<(Entity, &RpgEntity)>,
+        mut query: Query<(Entity, &mut RpgEntity)>,
         mut attack_events: EventReader<AttackEvent>,
         mut damage_event: EventWriter<DamageEvent>,
         item_manager: Res<ItemManager>,
     ) {
         for &AttackEvent { attacker, victim } in attack_events.read() {
-            let [attacker, victim] = query.get_many([attacker, victim]).unwrap();
+            let [mut attacker, victim] = query.get_many_mut([attacker, victim]).unwrap();
             let damage = attacker.1.attack_damage(&item_manager);
             info!(
                 "{:?} attacked {:?} for {damage:?} damage",
                 attacker.1.name(),
                 victim.1.name()
             );
+            attacker.1.wear_weapon();
 
             damage_event.write(DamageEvent {
                 victim: victim.0,
@@ -46,13 +50,14 @@ impl DamageEvent {
         mut query: Query<(Entity, &mut RpgEntity)>,
         mut damage_events: EventReader<DamageEvent>,
         mut death_event: EventWriter<DeathEvent>,
+        item_manager: Res<ItemManager>,
     ) {
         for &DamageEvent { victim, damage } in damage_events.read() {
             let mut victim = query.get_mut(victim).unwrap();
             let DamageResult {
                 reduced_damage,
                 life_status,
-            } = victim.1.apply_damage(damage);
+            } = victim.1.apply_damage(damage, &item_manager);
             info!(
                 "{:?} received {damage:?} (reduced: {reduced_damage:?}) damage, health is now: {:?}",
                 victim.1.name(),
@@ -69,10 +74,33 @@ impl DamageEvent {
 pub struct DeathEvent(pub Entity);
 
 impl DeathEvent {
-    pub fn handler(query: Query<&RpgEntity>, mut death_events: EventReader<DeathEvent>) {
+    pub fn handler(
+        mut query: Query<(&mut RpgEntity, Option<&Npc>)>,
+        mut death_events: EventReader<DeathEvent>,
+        mut reward_experience_event: EventWriter<RewardExperienceEvent>,
+        battle: Option<Res<Battle>>,
+        experience_curve: Res<ExperienceCurve>,
+        item_manager: Res<ItemManager>,
+        mut rng: Rng,
+    ) {
         for &DeathEvent(entity) in death_events.read() {
-            let victim = query.get(entity).unwrap();
+            let (mut victim, npc) = query.get_mut(entity).unwrap();
             info!("{:?} has died", victim.name());
+            // corpses drop the loot table named after the character, if there is one
+            let corpse_loot = npc.map(|npc| LootTableId(npc.id.0.clone()));
+            if let Some(table_id) =
+                corpse_loot.filter(|table_id| item_manager.get_loot_table(table_id).is_some())
+            {
+                for instance in item_manager.roll_loot(&table_id, None, &mut **rng) {
+                    info!("{:?} dropped {:?}", victim.name(), instance.item_id());
+                    victim.inventory.insert(instance);
+                }
+            }
+            if battle.as_ref().is_some_and(|battle| battle.0 == entity) {
+                reward_experience_event.write(RewardExperienceEvent(
+                    experience_curve.defeat_experience(victim.level()),
+                ));
+            }
         }
     }
 }
@@ -86,6 +114,7 @@ impl PlaySceneEvent {
         scene_manager: Res<SceneManager>,
         mut play_scene_events: EventReader<PlaySceneEvent>,
         mut spawn_npc_event: EventWriter<SpawnNpcEvent>,
+        mut spawn_vendor_event: EventWriter<SpawnVendorEvent>,
         mut state_manager: ResMut<StateManager>,
     ) {
         let play_scene_events = play_scene_events.read();
@@ -93,9 +122,11 @@ impl PlaySceneEvent {
             warn!("more than one play scene event is queued")
         }
         if let Some(play_scene_event) = play_scene_events.last() {
-            if let Some(scene_player) =
-                scene_manager.play_scene(play_scene_event.0.clone(), &mut spawn_npc_event)
-            {
+            if let Some(scene_player) = scene_manager.play_scene(
+                play_scene_event.0.clone(),
+                &mut spawn_npc_event,
+                &mut spawn_vendor_event,
+            ) {
                 info!("playing scene: {:?}", play_scene_event.0);
                 commands.insert_resource(scene_player);
                 state_manager.push(&mut commands, GameState::Dialogue);
@@ -114,16 +145,23 @@ impl EndSceneEvent {
         mut commands: Commands,
         mut end_scene_events: EventReader<EndSceneEvent>,
         mut state_manager: ResMut<StateManager>,
+        scene_player: Option<Res<ScenePlayer>>,
+        mut coverage: ResMut<DialogueCoverage>,
     ) {
-        let end_scene_events = end_scene_events.read();
-        if end_scene_events.len() > 0 {
-            if end_scene_events.len() > 1 {
+        // reading without consuming would end the scene again next frame
+        let end_scene_events = end_scene_events.read().count();
+        if end_scene_events > 0 {
+            if end_scene_events > 1 {
                 warn!("more than one end scene event is queued")
             }
             assert!(matches!(
                 state_manager.pop(&mut commands),
                 Some(GameState::Dialogue)
             ));
+            // the last response chosen may not have been recorded yet
+            if let Some(scene_player) = scene_player {
+                coverage.record(&scene_player);
+            }
             commands.remove_resource::<ScenePlayer>();
         }
     }
@@ -137,8 +175,7 @@ impl StaticCommandsEvent {
         scene_player: Option<ResMut<ScenePlayer>>,
         mut scene_manager: ResMut<SceneManager>,
         mut static_commands_events: EventReader<StaticCommandsEvent>,
-        mut start_battle_event: EventWriter<StartBattleEvent>,
-        mut update_npc_event: EventWriter<UpdateNpcEvent>,
+        mut writers: StaticCommandsWriters,
     ) {
         let Some(mut scene_player) = scene_player else {
             // if no scene is currently playing then we shouldn't have any events to handle.
@@ -156,13 +193,178 @@ impl StaticCommandsEvent {
                 bookmark.to_owned(),
                 commands.to_owned(),
                 &mut scene_manager,
-                &mut start_battle_event,
-                &mut update_npc_event,
+                &mut writers,
+            );
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct RewardItemEvent {
+    pub item_id: ItemId,
+    pub count: u32,
+}
+
+impl RewardItemEvent {
+    pub fn handler(
+        mut player_query: Query<&mut RpgEntity, With<Player>>,
+        mut reward_item_events: EventReader<RewardItemEvent>,
+        item_manager: Res<ItemManager>,
+    ) {
+        let Ok(mut player) = player_query.single_mut() else {
+            error!("cannot reward items. no player exists");
+            return;
+        };
+        for RewardItemEvent { item_id, count } in reward_item_events.read() {
+            info!("rewarding {count} {item_id:?}");
+            for instance in item_manager.spawn_many(item_id.to_owned(), *count) {
+                player.inventory.insert(instance);
+            }
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct RewardLootEvent(pub LootTableId);
+
+impl RewardLootEvent {
+    pub fn handler(
+        mut player_query: Query<&mut RpgEntity, With<Player>>,
+        mut reward_loot_events: EventReader<RewardLootEvent>,
+        item_manager: Res<ItemManager>,
+        mut rng: Rng,
+    ) {
+        let Ok(mut player) = player_query.single_mut() else {
+            error!("cannot reward loot. no player exists");
+            return;
+        };
+        for RewardLootEvent(loot_table_id) in reward_loot_events.read() {
+            for instance in item_manager.roll_loot(loot_table_id, None, &mut **rng) {
+                info!("rewarding {} {:?}", instance.quantity(), instance.item_id());
+                player.inventory.insert(instance);
+            }
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct RemoveItemEvent {
+    pub item_id: ItemId,
+    pub count: u32,
+    pub recipient: Option<NpcId>,
+}
+
+impl RemoveItemEvent {
+    pub fn handler(
+        mut player_query: Query<&mut RpgEntity, With<Player>>,
+        mut npc_query: Query<(&Npc, &mut RpgEntity), Without<Player>>,
+        mut remove_item_events: EventReader<RemoveItemEvent>,
+    ) {
+        let Ok(mut player) = player_query.single_mut() else {
+            error!("cannot remove items. no player exists");
+            return;
+        };
+        for RemoveItemEvent {
+            item_id,
+            count,
+            recipient,
+        } in remove_item_events.read()
+        {
+            let removed = player.remove_items(item_id, *count);
+            let removed_count: u32 = removed.iter().map(|instance| instance.quantity()).sum();
+            if removed_count < *count {
+                warn!("only removed {removed_count} of {count} {item_id:?} from player");
+            } else {
+                info!("removed {removed_count} {item_id:?} from player");
+            }
+
+            let Some(recipient) = recipient else {
+                continue;
+            };
+            if let Some((_npc, mut rpg_entity)) =
+                npc_query.iter_mut().find(|(npc, _)| &npc.id == recipient)
+            {
+                info!("giving {removed_count} {item_id:?} to {recipient:?}");
+                for instance in removed {
+                    rpg_entity.inventory.insert(instance);
+                }
+            } else {
+                error!("unable to give items: Could not find NPC with ID: {recipient:?}")
+            }
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct RewardExperienceEvent(pub u32);
+
+impl RewardExperienceEvent {
+    pub fn handler(
+        mut player_query: Query<(Entity, &mut RpgEntity), With<Player>>,
+        mut reward_experience_events: EventReader<RewardExperienceEvent>,
+        mut level_up_event: EventWriter<LevelUpEvent>,
+        experience_curve: Res<ExperienceCurve>,
+    ) {
+        let Ok((entity, mut player)) = player_query.single_mut() else {
+            error!("cannot reward experience. no player exists");
+            return;
+        };
+        for &RewardExperienceEvent(amount) in reward_experience_events.read() {
+            info!("rewarding {amount} experience");
+            let levels_gained = player.gain_experience(amount, &experience_curve);
+            if levels_gained > 0 {
+                level_up_event.write(LevelUpEvent {
+                    entity,
+                    level: player.level(),
+                    levels_gained,
+                });
+            }
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct LevelUpEvent {
+    pub entity: Entity,
+    pub level: u32,
+    pub levels_gained: u32,
+}
+
+impl LevelUpEvent {
+    pub fn handler(query: Query<&RpgEntity>, mut level_up_events: EventReader<LevelUpEvent>) {
+        for LevelUpEvent { entity, level, .. } in level_up_events.read() {
+            let Ok(rpg_entity) = query.get(*entity) else {
+                continue;
+            };
+            info!(
+                "{:?} reached level {level}, max health is now: {:?}",
+                rpg_entity.name(),
+                rpg_entity.max_health()
             );
         }
     }
 }
 
+#[derive(Event)]
+pub struct PlaySoundEvent {
+    pub sound: String,
+    pub volume: Option<f32>,
+}
+
+impl PlaySoundEvent {
+    pub fn handler(
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        packs: Res<AssetPacks>,
+        mut play_sound_events: EventReader<PlaySoundEvent>,
+    ) {
+        for PlaySoundEvent { sound, volume } in play_sound_events.read() {
+            info!("playing sound: {sound:?}");
+            play_sound_effect(&mut commands, &asset_server, &packs, sound, *volume);
+        }
+    }
+}
+
 #[derive(Event)]
 pub struct StartBattleEvent(pub NpcId);
 
@@ -222,13 +424,15 @@ impl SpawnNpcEvent {
         mut commands: Commands,
         npc_query: Query<&Npc>,
         mut spawn_npc_events: EventReader<SpawnNpcEvent>,
+        experience_curve: Res<ExperienceCurve>,
     ) {
         for SpawnNpcEvent(npc_id, character) in spawn_npc_events.read() {
             if utils::spawn_npc(
                 &mut commands,
-                npc_query,
+                &npc_query,
                 npc_id.to_owned(),
                 character.clone(),
+                &experience_curve,
             ) {
                 info!("spawned NPC: {npc_id:?}");
             } else {
@@ -238,6 +442,117 @@ impl SpawnNpcEvent {
     }
 }
 
+#[derive(Event)]
+pub struct SpawnVendorEvent(pub VendorId, pub Vendor);
+
+impl SpawnVendorEvent {
+    /// Spawns each vendor once, stocked with a fresh roll of its inventory.
+    pub fn handler(
+        mut commands: Commands,
+        shopkeeper_query: Query<&Shopkeeper>,
+        mut spawn_vendor_events: EventReader<SpawnVendorEvent>,
+        item_manager: Res<ItemManager>,
+        mut rng: Rng,
+    ) {
+        for SpawnVendorEvent(vendor_id, vendor) in spawn_vendor_events.read() {
+            if shopkeeper_query
+                .iter()
+                .any(|shopkeeper| &shopkeeper.id == vendor_id)
+            {
+                info!("skipped spawning vendor: {vendor_id:?}");
+                continue;
+            }
+            let mut inventory = Inventory::default();
+            for instance in vendor.roll_inventory(vendor_id, &item_manager, &mut **rng) {
+                inventory.insert(instance);
+            }
+            info!("spawned vendor: {vendor_id:?}");
+            commands.spawn((
+                Shopkeeper {
+                    id: vendor_id.to_owned(),
+                    name: vendor.name().to_string(),
+                    gold: vendor.daily_gold(),
+                },
+                inventory,
+            ));
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct EnterMapEvent {
+    pub map: MapId,
+    /// Where the player arrives. Defaults to the map's player position.
+    pub position: Option<Position>,
+}
+
+impl EnterMapEvent {
+    pub fn handler(
+        mut commands: Commands,
+        mut enter_map_events: EventReader<EnterMapEvent>,
+        map_manager: Res<MapManager>,
+    ) {
+        if let Some(EnterMapEvent {
+            map: map_id,
+            position,
+        }) = enter_map_events.read().last()
+        {
+            let map = map_manager.get_map(map_id);
+            if map.is_none() {
+                warn!("entering map {map_id:?}, which doesn't exist");
+            }
+            info!("entering map: {map_id:?}");
+            commands.insert_resource(CurrentMap(map_id.clone()));
+            match position.or(map.map(|map| map.player_position())) {
+                Some(position) => commands.insert_resource(PlayerPosition(position)),
+                None => commands.remove_resource::<PlayerPosition>(),
+            }
+        }
+    }
+}
+
+/// Writes the game as it is to a save slot, which `--save <slot>` starts from.
+#[derive(Event)]
+pub struct SaveGameEvent {
+    pub slot: String,
+}
+
+impl SaveGameEvent {
+    pub fn handler(
+        mut save_game_events: EventReader<SaveGameEvent>,
+        manifest: Res<GameManifest>,
+        scene_manager: Option<Res<SceneManager>>,
+        current_map: Option<Res<CurrentMap>>,
+        player_position: Option<Res<PlayerPosition>>,
+        history: Option<Res<DialogueHistory>>,
+        player_query: Query<&RpgEntity, With<Player>>,
+        mut toasts: ResMut<Toasts>,
+    ) {
+        for SaveGameEvent { slot } in save_game_events.read() {
+            let mut save = GameStart {
+                map: current_map
+                    .as_ref()
+                    .map(|current_map| current_map.0.clone()),
+                position: player_position.as_ref().map(|position| position.0),
+                player: player_query.single().ok().cloned(),
+                history: history.as_deref().cloned().unwrap_or_default(),
+                ..default()
+            };
+            if let Some(scene_manager) = &scene_manager {
+                save.variables = scene_manager.variables.clone();
+                save.entries = scene_manager.entries.clone();
+            }
+            match save.write_save(&manifest.assets.base, slot) {
+                Ok(path) => toasts.push(format!("saved to {}", path.display())),
+                Err(e) => {
+                    error!("could not save: {e}");
+                    toasts.push(format!("could not save: {e}"));
+                }
+            }
+        }
+    }
+}
+
 #[derive(Event)]
 pub struct UpdateNpcEvent(pub NpcId, pub CharacterUpdate);
 
@@ -0,0 +1,72 @@
+use bevy::prelude::Resource;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+#[derive(Resource, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct ExperienceCurve {
+    /// Experience needed to go from level 1 to level 2.
+    pub base: f32,
+    /// How steeply the experience needed grows with each level.
+    pub exponent: f32,
+    pub base_health: f32,
+    pub health_per_level: f32,
+    pub points_per_level: u32,
+    /// Experience for defeating an NPC, multiplied by the NPC's level.
+    pub defeat_experience_per_level: u32,
+}
+
+impl Default for ExperienceCurve {
+    fn default() -> Self {
+        Self {
+            base: 100.0,
+            exponent: 1.5,
+            base_health: 20.0,
+            health_per_level: 5.0,
+            points_per_level: 1,
+            defeat_experience_per_level: 25,
+        }
+    }
+}
+
+impl ExperienceCurve {
+    /// Experience needed to advance from `level` to the next level. Always at
+    /// least 1, so curves that round to nothing can't level up forever.
+    pub fn experience_to_next(&self, level: u32) -> u32 {
+        ((self.base * (level.max(1) as f32).powf(self.exponent)).round() as u32).max(1)
+    }
+
+    pub fn max_health(&self, level: u32) -> f32 {
+        self.base_health + self.health_per_level * level.saturating_sub(1) as f32
+    }
+
+    pub fn defeat_experience(&self, level: u32) -> u32 {
+        self.defeat_experience_per_level
+            .saturating_mul(level.max(1))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ExperienceCurve, RpgEntity};
+
+    #[test]
+    fn curves_without_experience_still_need_some() {
+        let curve = ExperienceCurve {
+            base: 0.0,
+            ..Default::default()
+        };
+        let mut player = RpgEntity::new(None, &curve);
+        assert_eq!(player.gain_experience(3, &curve), 3);
+        assert_eq!(curve.defeat_experience(u32::MAX), u32::MAX);
+    }
+
+    #[test]
+    fn experience_saturates() {
+        let curve = ExperienceCurve::default();
+        let mut player = RpgEntity::new(None, &curve);
+        player.gain_experience(u32::MAX, &curve);
+        player.gain_experience(u32::MAX, &curve);
+        assert!(player.experience() < curve.experience_to_next(player.level()));
+    }
+}
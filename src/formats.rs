@@ -0,0 +1,199 @@
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+
+use crate::{ItemFile, Map, Scene, locate, narrow_error};
+
+/// File formats scenes, items and maps can be written in. They all share the
+/// JSON schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFormat {
+    Json,
+    Yaml,
+    Ron,
+}
+
+#[derive(Debug, derive_more::From, derive_more::Display)]
+pub enum FormatError {
+    #[display("could not read file: {_0}")]
+    Io(std::io::Error),
+    #[display("invalid JSON: {_0}")]
+    Json(serde_json::Error),
+    #[display("invalid YAML: {_0}")]
+    Yaml(serde_yaml::Error),
+    #[display("invalid RON: {_0}")]
+    Ron(ron::error::SpannedError),
+    #[display("could not write RON: {_0}")]
+    RonWrite(ron::Error),
+    #[display("unknown file format: {_0}")]
+    UnknownFormat(String),
+    /// The document doesn't match the type it's read as. `pointer` is a JSON
+    /// pointer to the offending value, e.g. `/dialogue/dig/lines/2`.
+    #[from(skip)]
+    #[display(
+        "invalid data at {}: {error}",
+        if pointer.is_empty() { "the top level" } else { pointer.as_str() }
+    )]
+    Invalid {
+        pointer: String,
+        location: Option<(usize, usize)>,
+        error: serde_json::Error,
+    },
+}
+
+impl std::error::Error for FormatError {}
+
+impl FormatError {
+    /// The line and column the error is at, counting from 1, if the format
+    /// reports one.
+    pub fn location(&self) -> Option<(usize, usize)> {
+        match self {
+            FormatError::Json(error) if error.line() > 0 => Some((error.line(), error.column())),
+            FormatError::Invalid { location, .. } => *location,
+            FormatError::Yaml(error) => error
+                .location()
+                .map(|location| (location.line(), location.column())),
+            FormatError::Ron(error) => Some((error.position.line, error.position.col)),
+            _ => None,
+        }
+    }
+}
+
+impl DataFormat {
+    /// Extensions of every supported format.
+    pub const EXTENSIONS: [&str; 4] = ["json", "yaml", "yml", "ron"];
+
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "json" => Some(DataFormat::Json),
+            "yaml" | "yml" => Some(DataFormat::Yaml),
+            "ron" => Some(DataFormat::Ron),
+            _ => None,
+        }
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, FormatError> {
+        let path = path.as_ref();
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_extension)
+            .ok_or_else(|| FormatError::UnknownFormat(path.display().to_string()))
+    }
+
+    /// Reads a document into the JSON data model, whatever format it is in.
+    pub fn to_value(self, bytes: &[u8]) -> Result<serde_json::Value, FormatError> {
+        Ok(match self {
+            DataFormat::Json => serde_json::from_slice(bytes)?,
+            DataFormat::Yaml => serde_yaml::from_slice(bytes)?,
+            DataFormat::Ron => ron::de::from_bytes(bytes)?,
+        })
+    }
+
+    /// Going through the JSON data model means serde aliases and defaults work
+    /// the same in every format.
+    pub fn parse<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, FormatError> {
+        let value = self.to_value(bytes)?;
+        serde_path_to_error::deserialize(&value).map_err(|error| {
+            let (pointer, error) = narrow_error(&value, error);
+            // only JSON keeps the text close enough to the data to find it again
+            let location = match self {
+                DataFormat::Json => std::str::from_utf8(bytes)
+                    .ok()
+                    .and_then(|json| locate(json, &pointer)),
+                _ => None,
+            };
+            FormatError::Invalid {
+                pointer,
+                location,
+                error,
+            }
+        })
+    }
+
+    pub fn write(self, value: &serde_json::Value) -> Result<String, FormatError> {
+        Ok(match self {
+            DataFormat::Json => serde_json::to_string_pretty(value)? + "\n",
+            DataFormat::Yaml => serde_yaml::to_string(value)?,
+            DataFormat::Ron => {
+                ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())? + "\n"
+            }
+        })
+    }
+}
+
+/// Reads `path` as whichever format its extension says it is in.
+pub fn read_data<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> Result<T, FormatError> {
+    let format = DataFormat::from_path(&path)?;
+    format.parse(&std::fs::read(path)?)
+}
+
+/// Rewrites `input` as `output`, each in the format of its extension. Files
+/// named like `*.scene.*`, `*.items.*` or `*.map.*` are checked against their
+/// schema first, so a broken file isn't converted.
+pub fn convert<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q) -> anyhow::Result<()> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    let value = DataFormat::from_path(input)?.to_value(&std::fs::read(input)?)?;
+
+    let stem = input
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    if stem.ends_with(".scene") {
+        serde_json::from_value::<Scene>(value.clone())?;
+    } else if stem.ends_with(".items") {
+        serde_json::from_value::<ItemFile>(value.clone())?;
+    } else if stem.ends_with(".map") {
+        serde_json::from_value::<Map>(value.clone())?;
+    }
+
+    std::fs::write(output, DataFormat::from_path(output)?.write(&value)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{DataFormat, Scene, read_data};
+
+    #[test]
+    fn scenes_read_the_same_in_every_format() {
+        let scene: Scene = read_data(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/assets/scenes/test.scene.json"
+        ))
+        .unwrap();
+        let value = DataFormat::Json
+            .to_value(
+                &std::fs::read(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/assets/scenes/test.scene.json"
+                ))
+                .unwrap(),
+            )
+            .unwrap();
+
+        for format in [DataFormat::Yaml, DataFormat::Ron] {
+            let text = format.write(&value).unwrap();
+            let converted: Scene = format.parse(text.as_bytes()).unwrap();
+            assert_eq!(converted, scene, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn converting_keeps_key_order() {
+        let value = DataFormat::Json
+            .to_value(br#"{"to": "mike", "from": "jake", "all": {"zed": 1, "abe": 2}}"#)
+            .unwrap();
+        let json = DataFormat::Json.write(&value).unwrap();
+        assert!(json.find("to") < json.find("from") && json.find("zed") < json.find("abe"));
+
+        for format in [DataFormat::Yaml, DataFormat::Ron] {
+            let text = format.write(&value).unwrap();
+            let round_trip = format.to_value(text.as_bytes()).unwrap();
+            assert_eq!(
+                DataFormat::Json.write(&round_trip).unwrap(),
+                json,
+                "{format:?}"
+            );
+        }
+    }
+}
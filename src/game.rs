@@ -0,0 +1,412 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{platform::collections::HashMap, prelude::*};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ASSETS_PATH, AssetPacks, Character, DialogueHistory, EnterMapEvent, ExperienceCurve, ItemId,
+    ItemManager, MapId, Npc, NpcId, PlaySceneEvent, Player, Position, RpgEntity, SceneId,
+    SceneManager, SceneSectionId, Stats, read_data, utils,
+};
+
+/// The game manifest read at startup, unless `--game` names another.
+pub const GAME_MANIFEST_FILE: &str = "game.json";
+/// Where `--save` looks for save slots, relative to the assets folder.
+pub const SAVES_PATH: &str = "../saves";
+/// The save slot F5 writes to.
+pub const QUICK_SAVE_SLOT: &str = "quicksave";
+
+/// Who the player is, which characters exist everywhere, and where the game
+/// starts. Lets games and test fixtures share the engine without editing Rust.
+#[derive(Resource, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct GameManifest {
+    pub player: PlayerSetup,
+    /// Characters that exist in every scene, like the narrator.
+    #[serde(default)]
+    #[schemars(with = "std::collections::HashMap<NpcId, Character>")]
+    pub characters: HashMap<NpcId, Character>,
+    /// Where the game starts. Without a map or scene it starts on the map
+    /// screen with no map loaded.
+    #[serde(default)]
+    pub start: GameStart,
+    #[serde(default)]
+    pub assets: AssetPaths,
+    /// How levels, health and experience rewards scale.
+    #[serde(default)]
+    pub experience: ExperienceCurve,
+}
+
+impl Default for GameManifest {
+    fn default() -> Self {
+        Self {
+            player: PlayerSetup::default(),
+            characters: HashMap::from_iter([(
+                NpcId(String::from("narrator")),
+                Character {
+                    name: String::from(""),
+                    ..default()
+                },
+            )]),
+            start: GameStart::default(),
+            assets: AssetPaths::default(),
+            experience: ExperienceCurve::default(),
+        }
+    }
+}
+
+impl GameManifest {
+    /// Reads a manifest, in any data format. Its asset paths are made relative
+    /// to the folder it is in.
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        info!("loading game manifest: {path:?}");
+        let mut manifest: Self =
+            read_data(path).map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        manifest.assets.base = dir.join(&manifest.assets.base);
+        for pack in &mut manifest.assets.packs {
+            *pack = dir.join(&*pack);
+        }
+        Ok(manifest)
+    }
+
+    /// Loads the manifests of the base game and every pack, followed by
+    /// `extra_packs`.
+    pub fn asset_packs(&self, extra_packs: &[PathBuf]) -> anyhow::Result<AssetPacks> {
+        let pack_paths: Vec<PathBuf> = self
+            .assets
+            .packs
+            .iter()
+            .chain(extra_packs)
+            .cloned()
+            .collect();
+        AssetPacks::load(&self.assets.base, &pack_paths)
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PlayerSetup {
+    pub name: String,
+    /// Items the player carries but doesn't have equipped.
+    #[serde(default)]
+    pub inventory: Vec<ItemId>,
+    #[serde(default)]
+    pub equipped: Vec<ItemId>,
+    pub level: Option<u32>,
+    #[serde(default)]
+    pub stats: Stats,
+}
+
+impl Default for PlayerSetup {
+    fn default() -> Self {
+        Self {
+            name: String::from("Jake"),
+            inventory: Vec::new(),
+            equipped: vec![ItemId::new("dragonbone-sword")],
+            level: None,
+            stats: Stats::default(),
+        }
+    }
+}
+
+impl PlayerSetup {
+    /// Spawns the player. Items missing from `item_manager` are logged and
+    /// left out.
+    pub fn spawn(
+        &self,
+        commands: &mut Commands,
+        item_manager: &ItemManager,
+        curve: &ExperienceCurve,
+    ) {
+        let mut player = RpgEntity::new(Some(self.name.clone()), curve);
+        if let Some(level) = self.level {
+            player.set_level(level, curve);
+        }
+        player.set_stats(self.stats);
+        let items = self.inventory.iter().map(|id| (id, false));
+        for (item_id, equip) in items.chain(self.equipped.iter().map(|id| (id, true))) {
+            let Some(item) = item_manager.spawn(item_id.clone()) else {
+                warn!("the player starts with item {item_id:?}, which doesn't exist");
+                continue;
+            };
+            let instance_id = player.inventory.insert(item);
+            if equip {
+                player.equip(instance_id);
+            }
+        }
+        commands.spawn((Player, player));
+    }
+}
+
+/// Where the game starts and what has happened so far. Save slots are written
+/// the same way.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct GameStart {
+    /// The map to explore.
+    pub map: Option<MapId>,
+    /// Where the player is on `map`. Defaults to the map's player position.
+    #[serde(alias = "pos")]
+    pub position: Option<Position>,
+    /// A scene to play as soon as the game data has loaded, on top of `map`.
+    pub scene: Option<SceneId>,
+    /// The section `scene` starts in, instead of where it would resume.
+    pub section: Option<SceneSectionId>,
+    #[serde(default, alias = "vars")]
+    #[schemars(with = "std::collections::HashMap<String, String>")]
+    pub variables: HashMap<String, String>,
+    /// The section each scene resumes at.
+    #[serde(default)]
+    #[schemars(with = "std::collections::HashMap<SceneId, SceneSectionId>")]
+    pub entries: HashMap<SceneId, SceneSectionId>,
+    /// The player as they were when the game was saved, in place of the
+    /// manifest's player setup.
+    pub player: Option<RpgEntity>,
+    /// The dialogue shown before the game was saved.
+    #[serde(default)]
+    pub history: DialogueHistory,
+}
+
+impl GameStart {
+    /// Reads the save in `slot`, from `<slot>.save.json` in the saves folder
+    /// next to `assets_path`.
+    pub fn load_save(assets_path: &Path, slot: &str) -> anyhow::Result<Self> {
+        let path = Self::save_path(assets_path, slot);
+        info!("loading save: {path:?}");
+        read_data(&path).map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))
+    }
+
+    /// Writes this to `slot`, where [`GameStart::load_save`] reads it from.
+    /// Returns the path of the save.
+    pub fn write_save(&self, assets_path: &Path, slot: &str) -> anyhow::Result<PathBuf> {
+        let path = Self::save_path(assets_path, slot);
+        info!("writing save: {path:?}");
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)? + "\n")
+            .map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+        Ok(path)
+    }
+
+    fn save_path(assets_path: &Path, slot: &str) -> PathBuf {
+        assets_path
+            .join(SAVES_PATH)
+            .join(format!("{slot}.save.json"))
+    }
+
+    /// Applies `other` on top of this. Its map, position, scene, player and
+    /// history replace these, and its variables and entries are added to
+    /// them.
+    pub fn merge(&mut self, other: GameStart) {
+        // a position belongs to the map it was given for, and a section to
+        // its scene
+        if other.map.is_some() {
+            self.map = other.map;
+            self.position = other.position;
+        } else if other.position.is_some() {
+            self.position = other.position;
+        }
+        if other.scene.is_some() {
+            self.scene = other.scene;
+            self.section = other.section;
+        } else if other.section.is_some() {
+            self.section = other.section;
+        }
+        self.variables.extend(other.variables);
+        self.entries.extend(other.entries);
+        if other.player.is_some() {
+            self.player = other.player;
+        }
+        if !other.history.entries().is_empty() {
+            self.history = other.history;
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AssetPaths {
+    /// The base game's asset folder. Defaults to gizmo's own.
+    #[serde(default = "AssetPaths::default_base")]
+    pub base: PathBuf,
+    /// Asset packs loaded on top of the base game, in load order.
+    #[serde(default)]
+    pub packs: Vec<PathBuf>,
+}
+
+impl AssetPaths {
+    fn default_base() -> PathBuf {
+        PathBuf::from(ASSETS_PATH)
+    }
+}
+
+impl Default for AssetPaths {
+    fn default() -> Self {
+        Self {
+            base: Self::default_base(),
+            packs: Vec::new(),
+        }
+    }
+}
+
+/// Starts the game where the manifest says, once its data has loaded.
+pub fn start_game(
+    manifest: Res<GameManifest>,
+    scene_manager: Option<ResMut<SceneManager>>,
+    history: Option<ResMut<DialogueHistory>>,
+    mut enter_map_event: EventWriter<EnterMapEvent>,
+    mut play_scene_event: EventWriter<PlaySceneEvent>,
+) {
+    let start = &manifest.start;
+    if let Some(mut history) = history {
+        *history = start.history.clone();
+    }
+    if let Some(map_id) = &start.map {
+        enter_map_event.write(EnterMapEvent {
+            map: map_id.clone(),
+            position: start.position,
+        });
+    }
+
+    if let Some(mut scene_manager) = scene_manager {
+        scene_manager.update_variables(start.variables.clone());
+        for (scene_id, section_id) in &start.entries {
+            scene_manager.update_scene_entry(scene_id.clone(), section_id.clone());
+        }
+        if let (Some(scene_id), Some(section_id)) = (&start.scene, &start.section) {
+            let has_section = scene_manager
+                .get_scene(scene_id)
+                .is_some_and(|scene| scene.dialogue.contains_key(section_id));
+            if has_section {
+                scene_manager.update_scene_entry(scene_id.clone(), section_id.clone());
+            } else {
+                warn!("scene {scene_id:?} has no section {section_id:?} to start in");
+            }
+        }
+    }
+    if let Some(scene_id) = &start.scene {
+        play_scene_event.write(PlaySceneEvent(scene_id.clone()));
+    }
+}
+
+/// Spawns the player, as saved or as the manifest sets them up, and global
+/// characters once the items they carry exist.
+pub fn spawn_characters(
+    mut commands: Commands,
+    manifest: Res<GameManifest>,
+    item_manager: Option<Res<ItemManager>>,
+    experience_curve: Option<Res<ExperienceCurve>>,
+    npc_query: Query<&Npc>,
+) {
+    let no_items = ItemManager::new();
+    let experience_curve = experience_curve.as_deref().cloned().unwrap_or_default();
+    match &manifest.start.player {
+        Some(player) => {
+            commands.spawn((Player, player.clone()));
+        }
+        None => manifest.player.spawn(
+            &mut commands,
+            item_manager.as_deref().unwrap_or(&no_items),
+            &experience_curve,
+        ),
+    }
+
+    for (npc_id, character) in &manifest.characters {
+        utils::spawn_npc(
+            &mut commands,
+            &npc_query,
+            npc_id.clone(),
+            character.clone(),
+            &experience_curve,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        DialogueHistory, ExperienceCurve, GameManifest, GameStart, ItemId, ItemManager, MapId,
+        Position, RpgEntity, SceneId, SceneSectionId,
+    };
+
+    #[test]
+    fn game_manifest_matches_the_defaults() {
+        let mut manifest =
+            GameManifest::load(concat!(env!("CARGO_MANIFEST_DIR"), "/game.json")).unwrap();
+        assert!(manifest.assets.base.ends_with("assets"));
+        assert!(manifest.asset_packs(&[]).is_ok());
+
+        manifest.assets = Default::default();
+        assert_eq!(manifest, GameManifest::default());
+    }
+
+    #[test]
+    fn later_starts_override_earlier_ones() {
+        let mut start = GameStart {
+            map: Some(MapId::new("village")),
+            position: Some(Position::new(1, 2)),
+            scene: Some(SceneId::new("mike")),
+            section: Some(SceneSectionId(String::from("dig"))),
+            ..Default::default()
+        };
+        start
+            .variables
+            .insert(String::from("met-mike"), String::from("true"));
+
+        let mut args = GameStart {
+            map: Some(MapId::new("forest")),
+            ..Default::default()
+        };
+        args.variables
+            .insert(String::from("gold"), String::from("5"));
+        start.merge(args);
+
+        assert_eq!(start.map, Some(MapId::new("forest")));
+        assert_eq!(start.position, None);
+        assert_eq!(start.scene, Some(SceneId::new("mike")));
+        assert_eq!(start.section, Some(SceneSectionId(String::from("dig"))));
+        assert_eq!(start.variables.len(), 2);
+        assert_eq!("3, 4".parse::<Position>().unwrap(), Position::new(3, 4));
+    }
+
+    #[test]
+    fn saves_keep_the_player() {
+        let item_manager = ItemManager::new()
+            .with_load_folder(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/items"))
+            .unwrap();
+        let curve = ExperienceCurve::default();
+        let mut player = RpgEntity::new(Some(String::from("Jake")), &curve);
+        player.gain_experience(250, &curve);
+        let sword = item_manager.spawn(ItemId::new("iron-sword")).unwrap();
+        let sword_id = player.inventory.insert(sword);
+        assert!(player.equip(sword_id));
+        let mut history = DialogueHistory::default();
+        history.record_response(&SceneId::new("mike"), "Hello");
+
+        let mut save = GameStart {
+            map: Some(MapId::new("village")),
+            position: Some(Position::new(1, 2)),
+            player: Some(player),
+            history,
+            ..Default::default()
+        };
+        save.variables
+            .insert(String::from("met-mike"), String::from("true"));
+        save.entries
+            .insert(SceneId::new("mike"), SceneSectionId(String::from("dig")));
+
+        let assets_path = std::env::temp_dir().join("gizmo-save-test/assets");
+        save.write_save(&assets_path, "test").unwrap();
+        let loaded = GameStart::load_save(&assets_path, "test").unwrap();
+        assert_eq!(loaded, save);
+
+        let mut start = GameStart::default();
+        start.merge(loaded);
+        assert_eq!(start.player, save.player);
+        assert_eq!(start.history.entries().len(), 1);
+    }
+}
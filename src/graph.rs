@@ -0,0 +1,327 @@
+use std::{fmt::Write, path::Path};
+
+use crate::{Condition, Response, Scene, SceneId, SceneManager, SceneSectionId};
+
+const LABEL_LENGTH: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GraphFormat {
+    #[default]
+    Dot,
+    Mermaid,
+}
+
+impl std::str::FromStr for GraphFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" | "graphviz" => Ok(GraphFormat::Dot),
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            _ => anyhow::bail!("unknown graph format: {s:?}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EdgeKind {
+    Response,
+    Continue,
+    Fail,
+    SceneEntry,
+}
+
+struct Edge {
+    from: String,
+    to: String,
+    label: String,
+    kind: EdgeKind,
+}
+
+fn shorten(text: &str) -> String {
+    if text.chars().count() > LABEL_LENGTH {
+        let short: String = text.chars().take(LABEL_LENGTH - 1).collect();
+        format!("{short}…")
+    } else {
+        text.to_string()
+    }
+}
+
+fn describe_condition(condition: &Condition) -> String {
+    let describe_all = |conditions: &[Condition]| {
+        conditions
+            .iter()
+            .map(describe_condition)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    match condition {
+        Condition::VarEquals { variable, value } => format!("{variable} = {value}"),
+        Condition::Any { conditions } => format!("any({})", describe_all(conditions)),
+        Condition::Not { conditions } => format!("not({})", describe_all(conditions)),
+        Condition::HasItem { item, count } => match count {
+            Some(count) if *count > 1 => format!("has {count} {item}"),
+            _ => format!("has {item}"),
+        },
+        Condition::QuestStage => "quest stage".to_string(),
+    }
+}
+
+fn describe_response(response: &Response) -> String {
+    let mut label = shorten(&response.text);
+    if !response.conditions.is_empty() {
+        let conditions: Vec<_> = response.conditions.iter().map(describe_condition).collect();
+        write!(label, " [if {}]", conditions.join(", ")).unwrap();
+    }
+    if let Some(skill_check) = &response.skill_check {
+        write!(label, " [{} check", skill_check.check).unwrap();
+        if let Some(modifier) = skill_check.modifier {
+            write!(label, " {modifier:+}").unwrap();
+        }
+        label.push(']');
+    }
+    label
+}
+
+impl Scene {
+    /// Sections in a stable order, with `start` first.
+    fn sorted_sections(&self) -> Vec<&SceneSectionId> {
+        let start = SceneSectionId::default();
+        let mut sections: Vec<_> = self.dialogue.keys().collect();
+        sections.sort_by_key(|section| (**section != start, section.0.clone()));
+        sections
+    }
+
+    fn graph_edges(&self) -> Vec<Edge> {
+        let mut edges = Vec::new();
+        for section in self.sorted_sections() {
+            let dialogue = &self.dialogue[section];
+            for response in &dialogue.responses {
+                if let Some(link) = &response.link {
+                    edges.push(Edge {
+                        from: section.0.clone(),
+                        to: link.0.clone(),
+                        label: describe_response(response),
+                        kind: EdgeKind::Response,
+                    });
+                }
+                if let Some(skill_check) = &response.skill_check {
+                    let fails = std::iter::once(("fail", &skill_check.link_fail)).chain(
+                        skill_check
+                            .link_crit_fail
+                            .iter()
+                            .map(|link| ("critical fail", link)),
+                    );
+                    for (label, link) in fails {
+                        edges.push(Edge {
+                            from: section.0.clone(),
+                            to: link.0.clone(),
+                            label: format!("{label}: {}", shorten(&response.text)),
+                            kind: EdgeKind::Fail,
+                        });
+                    }
+                }
+            }
+            if let Some(continue_to) = &dialogue.continue_to {
+                edges.push(Edge {
+                    from: section.0.clone(),
+                    to: continue_to.0.clone(),
+                    label: String::new(),
+                    kind: EdgeKind::Continue,
+                });
+            }
+        }
+
+        for (section, commands) in self.all_commands() {
+            let from = section.unwrap_or(&SceneSectionId::default()).0.clone();
+            let mut entries: Vec<_> = commands.scene_entry.iter().flatten().collect();
+            entries.sort_by_key(|(scene, _)| scene.0.clone());
+            for (scene, entry) in entries {
+                let to = if scene == self.id() {
+                    entry.0.clone()
+                } else {
+                    format!("{scene}:{entry}")
+                };
+                edges.push(Edge {
+                    from: from.clone(),
+                    to,
+                    label: format!("sets {scene} entry"),
+                    kind: EdgeKind::SceneEntry,
+                });
+            }
+        }
+        edges
+    }
+
+    /// Node names and labels. Sections are labelled with their first line.
+    fn graph_nodes(&self, edges: &[Edge]) -> Vec<(String, String)> {
+        let mut nodes: Vec<(String, String)> = self
+            .sorted_sections()
+            .into_iter()
+            .map(|section| {
+                let first_line = self.dialogue[section]
+                    .lines
+                    .first()
+                    .map(|line| format!("{}: {}", line.from, shorten(&line.text)))
+                    .unwrap_or_default();
+                (section.0.clone(), format!("{section}\n{first_line}"))
+            })
+            .collect();
+        // links to sections that don't exist, or to other scenes
+        for edge in edges {
+            if !nodes.iter().any(|(name, _)| *name == edge.to) {
+                nodes.push((edge.to.clone(), edge.to.clone()));
+            }
+        }
+        nodes
+    }
+
+    /// Renders the scene's dialogue as a Graphviz or Mermaid graph.
+    pub fn to_graph(&self, format: GraphFormat) -> String {
+        let edges = self.graph_edges();
+        let nodes = self.graph_nodes(&edges);
+        match format {
+            GraphFormat::Dot => render_dot(&self.id().0, &nodes, &edges),
+            GraphFormat::Mermaid => render_mermaid(&nodes, &edges),
+        }
+    }
+}
+
+fn render_dot(name: &str, nodes: &[(String, String)], edges: &[Edge]) -> String {
+    let escape = |s: &str| {
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    };
+    let mut dot = format!("digraph \"{}\" {{\n    node [shape=box];\n", escape(name));
+    for (node, label) in nodes {
+        writeln!(
+            dot,
+            "    \"{}\" [label=\"{}\"];",
+            escape(node),
+            escape(label)
+        )
+        .unwrap();
+    }
+    for edge in edges {
+        let style = match edge.kind {
+            EdgeKind::Response => "",
+            EdgeKind::Continue => ", style=bold",
+            EdgeKind::Fail => ", color=red",
+            EdgeKind::SceneEntry => ", style=dashed, color=blue",
+        };
+        writeln!(
+            dot,
+            "    \"{}\" -> \"{}\" [label=\"{}\"{style}];",
+            escape(&edge.from),
+            escape(&edge.to),
+            escape(&edge.label)
+        )
+        .unwrap();
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn render_mermaid(nodes: &[(String, String)], edges: &[Edge]) -> String {
+    // mermaid node IDs can't contain most punctuation, so number them
+    let id = |name: &str| {
+        nodes
+            .iter()
+            .position(|(node, _)| node == name)
+            .map(|i| format!("n{i}"))
+            .unwrap_or_default()
+    };
+    let escape = |s: &str| s.replace('"', "#quot;").replace('\n', "<br/>");
+    let mut mermaid = String::from("flowchart TD\n");
+    for (i, (_, label)) in nodes.iter().enumerate() {
+        writeln!(mermaid, "    n{i}[\"{}\"]", escape(label)).unwrap();
+    }
+    for edge in edges {
+        let arrow = match edge.kind {
+            EdgeKind::Response | EdgeKind::Fail => "-->",
+            EdgeKind::Continue => "==>",
+            EdgeKind::SceneEntry => "-.->",
+        };
+        let (from, to) = (id(&edge.from), id(&edge.to));
+        if edge.label.is_empty() {
+            writeln!(mermaid, "    {from} {arrow} {to}").unwrap();
+        } else {
+            writeln!(
+                mermaid,
+                "    {from} {arrow}|\"{}\"| {to}",
+                escape(&edge.label)
+            )
+            .unwrap();
+        }
+    }
+    mermaid
+}
+
+/// Loads the scenes under `assets_path` and renders the one with `scene_id`.
+pub fn export_graph(
+    assets_path: &Path,
+    scene_id: &SceneId,
+    format: GraphFormat,
+) -> anyhow::Result<String> {
+    let scene_manager = SceneManager::new().with_load_folder(assets_path.join("scenes"))?;
+    let Some(scene) = scene_manager.get_scene(scene_id) else {
+        anyhow::bail!("no scene with ID: {scene_id:?}");
+    };
+    Ok(scene.to_graph(format))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{GraphFormat, Scene};
+
+    fn scene() -> Scene {
+        serde_json::from_str(
+            r#"{
+                "id": "graph",
+                "dialogue": {
+                    "start": {
+                        "lines": [{ "from": "mike", "text": "Fight me." }],
+                        "responses": [
+                            {
+                                "text": "Okay",
+                                "lnk": "fight",
+                                "skill-check": { "check": "strength", "lnk-fail": "lost" }
+                            },
+                            {
+                                "text": "Pie?",
+                                "lnk": "pie",
+                                "cond": [{ "type": "has-item", "item": "apple-pie" }],
+                                "scene-entry": { "graph": "pie" }
+                            }
+                        ]
+                    },
+                    "fight": { "lines": [{ "from": "mike", "text": "Ow." }], "cont": "pie" },
+                    "lost": { "lines": [{ "from": "mike", "text": "Ha!" }] },
+                    "pie": { "lines": [{ "from": "mike", "text": "Yum." }] }
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn dot_has_every_edge() {
+        let dot = scene().to_graph(GraphFormat::Dot);
+        assert!(dot.starts_with("digraph \"graph\""));
+        assert!(dot.contains("\"start\" [label=\"start\\nmike: Fight me.\"]"));
+        assert!(dot.contains("\"start\" -> \"fight\" [label=\"Okay [Strength check]\"]"));
+        assert!(dot.contains("\"start\" -> \"lost\" [label=\"fail: Okay\", color=red]"));
+        assert!(dot.contains("\"start\" -> \"pie\" [label=\"Pie? [if has apple-pie]\"]"));
+        assert!(dot.contains("\"fight\" -> \"pie\" [label=\"\", style=bold]"));
+        assert!(dot.contains("[label=\"sets graph entry\", style=dashed, color=blue]"));
+    }
+
+    #[test]
+    fn mermaid_numbers_nodes() {
+        let mermaid = scene().to_graph(GraphFormat::Mermaid);
+        assert!(mermaid.starts_with("flowchart TD\n    n0[\"start<br/>mike: Fight me.\"]"));
+        assert!(mermaid.contains("n1 ==> n3"));
+        assert!(mermaid.contains("n0 -.->|\"sets graph entry\"| n3"));
+    }
+}
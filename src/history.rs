@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    FormattedLine, Npc, Player, RpgEntity, SceneBookmark, SceneId, SceneManager, ScenePlayer,
+};
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", tag = "type")]
+pub enum HistoryEntry {
+    Line {
+        scene: SceneId,
+        speaker: String,
+        text: String,
+    },
+    Response {
+        scene: SceneId,
+        text: String,
+    },
+}
+
+/// Every line shown and response chosen so far, oldest first. Saves keep it,
+/// so the backlog reaches back past the current play session.
+#[derive(Resource, Serialize, Deserialize, JsonSchema, Debug, Clone, Default, PartialEq, Eq)]
+pub struct DialogueHistory {
+    entries: Vec<HistoryEntry>,
+    /// The line recorded last, so a line on screen for several frames is only
+    /// recorded once.
+    #[serde(skip)]
+    last_line: Option<SceneBookmark>,
+}
+
+impl DialogueHistory {
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    pub fn record_line(
+        &mut self,
+        bookmark: &SceneBookmark,
+        scene: &SceneId,
+        speaker: &str,
+        text: &str,
+    ) {
+        if self.last_line.as_ref() == Some(bookmark) {
+            return;
+        }
+        self.last_line = Some(bookmark.clone());
+        self.entries.push(HistoryEntry::Line {
+            scene: scene.clone(),
+            speaker: speaker.to_string(),
+            text: text.to_string(),
+        });
+    }
+
+    pub fn record_response(&mut self, scene: &SceneId, text: &str) {
+        self.last_line = None;
+        self.entries.push(HistoryEntry::Response {
+            scene: scene.clone(),
+            text: text.to_string(),
+        });
+    }
+
+    /// Lets the next line be recorded even if it was the last one shown,
+    /// e.g. when a scene is played again.
+    pub fn break_line(&mut self) {
+        self.last_line = None;
+    }
+}
+
+/// Records the line on screen as it is shown, with the speaker's name and
+/// variables as they are right now.
+pub fn record_dialogue_history(
+    mut history: ResMut<DialogueHistory>,
+    scene_player: Option<Res<ScenePlayer>>,
+    scene_manager: Res<SceneManager>,
+    npc_query: Query<(&Npc, &RpgEntity)>,
+    player_query: Query<&RpgEntity, With<Player>>,
+) {
+    let Some(scene_player) = scene_player else {
+        return;
+    };
+    if scene_player.is_added() {
+        history.break_line();
+    }
+    let Some(line) = scene_player.current_line(&scene_manager) else {
+        return;
+    };
+    let speaker = npc_query
+        .iter()
+        .find(|(npc, _rpg_entity)| npc.id == line.from)
+        .map(|(_npc, rpg_entity)| rpg_entity.name())
+        .unwrap_or(line.from.0.as_str());
+    let player_name = player_query
+        .single()
+        .map(RpgEntity::name)
+        .unwrap_or_default();
+    history.record_line(
+        &scene_player.line_bookmark(),
+        scene_player.scene(),
+        speaker,
+        &FormattedLine::new(&line.text, &scene_manager, player_name).text,
+    );
+}
+
+/// Whether the dialogue backlog overlay is open.
+#[derive(Resource, Debug, Default)]
+pub struct ShowBacklog(pub bool);
+
+#[cfg(test)]
+mod test {
+    use crate::{DialogueHistory, HistoryEntry, SceneBookmark, SceneId};
+
+    #[test]
+    fn lines_are_recorded_once_per_showing() {
+        let scene = SceneId::new("test");
+        let bookmark = SceneBookmark::new(&scene, None, Some(0), None);
+        let mut history = DialogueHistory::default();
+
+        history.record_line(&bookmark, &scene, "Mike", "Hey.");
+        history.record_line(&bookmark, &scene, "Mike", "Hey.");
+        history.record_response(&scene, "Hi!");
+        history.record_line(&bookmark, &scene, "Mike", "Hey.");
+
+        assert_eq!(
+            history.entries(),
+            [
+                HistoryEntry::Line {
+                    scene: scene.clone(),
+                    speaker: "Mike".into(),
+                    text: "Hey.".into()
+                },
+                HistoryEntry::Response {
+                    scene: scene.clone(),
+                    text: "Hi!".into()
+                },
+                HistoryEntry::Line {
+                    scene: scene.clone(),
+                    speaker: "Mike".into(),
+                    text: "Hey.".into()
+                },
+            ]
+        );
+    }
+}
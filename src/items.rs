@@ -1,18 +1,33 @@
-use std::path::Path;
+use std::{collections::HashSet, path::Path};
 
 use bevy::{
-    asset::{Asset, uuid::Uuid},
-    log::{info, warn},
+    asset::{Asset, AssetEvent, AssetId, AssetServer, Assets, uuid::Uuid},
+    log::{error, info, warn},
     platform::collections::HashMap,
-    prelude::Resource,
-    reflect::Reflect,
+    prelude::{EventReader, Res, ResMut, Resource},
+    reflect::{Reflect, TypePath},
 };
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
-use crate::components::ArmorSlot;
+use crate::{
+    AssetPacks, LoadDiagnostic, LootTable, LootTableId, components::ArmorSlot, read_data, utils,
+};
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, derive_more::From, derive_more::Display)]
-pub struct ItemInstanceId(Uuid);
+#[derive(
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Debug,
+    derive_more::From,
+    derive_more::Display,
+)]
+pub struct ItemInstanceId(#[schemars(with = "String")] Uuid);
 
 impl Default for ItemInstanceId {
     fn default() -> Self {
@@ -26,11 +41,48 @@ impl ItemInstanceId {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug, Hash)]
 pub struct ItemInstance {
     instance_id: ItemInstanceId,
     item_id: ItemId,
     kind: ItemKind,
+    stackable: bool,
+    quantity: u32,
+    #[serde(default)]
+    modifiers: ItemModifiers,
+}
+
+/// Instance-level data layered over an item's definition.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug, Hash, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct ItemModifiers {
+    custom_name: Option<String>,
+    #[serde(default)]
+    enchantments: Vec<Enchantment>,
+    durability: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[serde(rename_all = "kebab-case", tag = "type")]
+pub enum Enchantment {
+    Damage { amount: u32 },
+    Defense { amount: u32 },
+}
+
+impl Enchantment {
+    pub fn damage(&self) -> u32 {
+        match self {
+            Enchantment::Damage { amount } => *amount,
+            Enchantment::Defense { .. } => 0,
+        }
+    }
+
+    pub fn defense(&self) -> u32 {
+        match self {
+            Enchantment::Damage { .. } => 0,
+            Enchantment::Defense { amount } => *amount,
+        }
+    }
 }
 
 impl ItemInstance {
@@ -45,9 +97,115 @@ impl ItemInstance {
     pub fn kind(&self) -> &ItemKind {
         &self.kind
     }
+
+    pub fn is_stackable(&self) -> bool {
+        self.stackable
+    }
+
+    pub fn quantity(&self) -> u32 {
+        self.quantity
+    }
+
+    /// The custom name if one is set, otherwise the definition's name.
+    pub fn name<'a>(&'a self, item: &'a AnyItem) -> &'a str {
+        self.modifiers.custom_name.as_deref().unwrap_or(item.name())
+    }
+
+    pub fn custom_name(&self) -> Option<&str> {
+        self.modifiers.custom_name.as_deref()
+    }
+
+    pub fn set_custom_name(&mut self, name: Option<String>) {
+        self.modifiers.custom_name = name;
+    }
+
+    pub fn enchantments(&self) -> &[Enchantment] {
+        &self.modifiers.enchantments
+    }
+
+    pub fn enchant(&mut self, enchantment: Enchantment) {
+        self.modifiers.enchantments.push(enchantment);
+    }
+
+    /// Remaining durability, or `None` if the item never wears out.
+    pub fn durability(&self) -> Option<u32> {
+        self.modifiers.durability
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.modifiers.durability == Some(0)
+    }
+
+    /// Reduces durability by `amount`. Returns `true` if this broke the item.
+    pub fn wear(&mut self, amount: u32) -> bool {
+        let Some(durability) = self.modifiers.durability.as_mut() else {
+            return false;
+        };
+        let was_broken = *durability == 0;
+        *durability = durability.saturating_sub(amount);
+        !was_broken && *durability == 0
+    }
+
+    pub fn repair(&mut self, item: &AnyItem) {
+        self.modifiers.durability = item.durability();
+    }
+
+    /// Damage including enchantments. Broken items deal no damage.
+    pub fn effective_damage(&self, item: &AnyItem) -> u32 {
+        if self.is_broken() {
+            return 0;
+        }
+        item.damage()
+            + self
+                .enchantments()
+                .iter()
+                .map(Enchantment::damage)
+                .sum::<u32>()
+    }
+
+    /// Defense including enchantments. Broken items provide no defense.
+    pub fn effective_defense(&self, item: &AnyItem) -> u32 {
+        if self.is_broken() {
+            return 0;
+        }
+        item.defense()
+            + self
+                .enchantments()
+                .iter()
+                .map(Enchantment::defense)
+                .sum::<u32>()
+    }
+
+    /// Returns `true` if `other` can be merged into this instance's stack.
+    pub fn stacks_with(&self, other: &ItemInstance) -> bool {
+        self.stackable
+            && other.stackable
+            && self.item_id == other.item_id
+            && self.modifiers == other.modifiers
+    }
+
+    pub(crate) fn merge(&mut self, other: ItemInstance) {
+        debug_assert!(self.stacks_with(&other));
+        self.quantity += other.quantity;
+    }
+
+    /// Detaches `count` items from this stack into a new instance.
+    ///
+    /// Returns `None` if `count` is zero or would leave this stack empty.
+    pub(crate) fn split_off(&mut self, count: u32) -> Option<ItemInstance> {
+        if count == 0 || count >= self.quantity {
+            return None;
+        }
+        self.quantity -= count;
+        Some(ItemInstance {
+            instance_id: ItemInstanceId::new(),
+            quantity: count,
+            ..self.clone()
+        })
+    }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug, Hash)]
 pub enum ItemKind {
     Apparel(ArmorSlot),
     Weapon,
@@ -56,13 +214,16 @@ pub enum ItemKind {
     Shield,
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
-struct ItemFile {
+/// A file of item and loot table definitions.
+#[derive(Asset, TypePath, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+pub struct ItemFile {
     apparel: Option<Vec<Apparel>>,
     weapon: Option<Vec<Weapon>>,
     food: Option<Vec<Food>>,
     potion: Option<Vec<Potion>>,
     shield: Option<Vec<Shield>>,
+    #[serde(alias = "loot-table")]
+    loot_table: Option<Vec<LootTable>>,
 }
 
 impl ItemFile {
@@ -93,7 +254,7 @@ impl ItemFile {
     }
 }
 
-#[derive(Deserialize, Reflect, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, JsonSchema, Reflect, Debug, Clone, PartialEq, Eq)]
 pub struct Apparel {
     id: String,
     name: String,
@@ -102,6 +263,9 @@ pub struct Apparel {
     defense: u32,
     weight: u32,
     value: u32,
+    durability: Option<u32>,
+    #[serde(default)]
+    stackable: bool,
 }
 
 impl Apparel {
@@ -124,15 +288,22 @@ impl Apparel {
     pub fn value(&self) -> u32 {
         self.value
     }
+
+    pub fn durability(&self) -> Option<u32> {
+        self.durability
+    }
 }
 
-#[derive(Deserialize, Reflect, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, JsonSchema, Reflect, Debug, Clone, PartialEq, Eq)]
 pub struct Weapon {
     id: String,
     name: String,
     damage: u32,
     weight: u32,
     value: u32,
+    durability: Option<u32>,
+    #[serde(default)]
+    stackable: bool,
 }
 
 impl Weapon {
@@ -155,9 +326,13 @@ impl Weapon {
     pub fn value(&self) -> u32 {
         self.value
     }
+
+    pub fn durability(&self) -> Option<u32> {
+        self.durability
+    }
 }
 
-#[derive(Deserialize, Reflect, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, JsonSchema, Reflect, Debug, Clone, PartialEq, Eq)]
 pub struct Food {
     id: String,
     name: String,
@@ -189,7 +364,7 @@ impl Food {
     }
 }
 
-#[derive(Deserialize, Reflect, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, JsonSchema, Reflect, Debug, Clone, PartialEq, Eq)]
 pub struct Potion {
     id: String,
     name: String,
@@ -220,18 +395,21 @@ impl Potion {
     }
 }
 
-#[derive(Deserialize, Reflect, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, JsonSchema, Reflect, Debug, Clone, PartialEq, Eq)]
 pub struct PotionEffects {
     health: Option<u32>,
 }
 
-#[derive(Deserialize, Reflect, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, JsonSchema, Reflect, Debug, Clone, PartialEq, Eq)]
 pub struct Shield {
     id: String,
     name: String,
     weight: u32,
     defense: u32,
     value: u32,
+    durability: Option<u32>,
+    #[serde(default)]
+    stackable: bool,
 }
 
 impl Shield {
@@ -254,10 +432,25 @@ impl Shield {
     pub fn value(&self) -> u32 {
         self.value
     }
+
+    pub fn durability(&self) -> Option<u32> {
+        self.durability
+    }
 }
 
 #[derive(
-    Debug, Hash, Clone, PartialEq, Eq, PartialOrd, Ord, derive_more::From, derive_more::Display,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    Debug,
+    Hash,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    derive_more::From,
+    derive_more::Display,
 )]
 pub struct ItemId(pub String);
 
@@ -320,6 +513,43 @@ impl AnyItem {
         }
     }
 
+    pub fn damage(&self) -> u32 {
+        match self {
+            AnyItem::Weapon(i) => i.damage,
+            _ => 0,
+        }
+    }
+
+    pub fn defense(&self) -> u32 {
+        match self {
+            AnyItem::Apparel(i) => i.defense,
+            AnyItem::Shield(i) => i.defense,
+            _ => 0,
+        }
+    }
+
+    /// Maximum durability, or `None` if the item never wears out.
+    pub fn durability(&self) -> Option<u32> {
+        match self {
+            AnyItem::Apparel(i) => i.durability,
+            AnyItem::Weapon(i) => i.durability,
+            AnyItem::Food(_) => None,
+            AnyItem::Potion(_) => None,
+            AnyItem::Shield(i) => i.durability,
+        }
+    }
+
+    /// Food and potions always stack. Other items stack when flagged `stackable`.
+    pub fn is_stackable(&self) -> bool {
+        match self {
+            AnyItem::Apparel(i) => i.stackable,
+            AnyItem::Weapon(i) => i.stackable,
+            AnyItem::Food(_) => true,
+            AnyItem::Potion(_) => true,
+            AnyItem::Shield(i) => i.stackable,
+        }
+    }
+
     pub fn is_apparel(&self) -> bool {
         matches!(self, Self::Apparel(_))
     }
@@ -394,6 +624,10 @@ impl AnyItem {
 #[derive(Resource, Debug, Clone, Default)]
 pub struct ItemManager {
     pub(crate) items: HashMap<ItemId, AnyItem>,
+    pub(crate) loot_tables: HashMap<LootTableId, LootTable>,
+    /// Every loaded item file asset and the asset pack it is from, so a
+    /// definition can fall back to another file's when its own file changes.
+    files: HashMap<AssetId<ItemFile>, (usize, ItemFile)>,
 }
 
 impl ItemManager {
@@ -403,32 +637,134 @@ impl ItemManager {
 
     pub fn load_items<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<()> {
         info!("loading items from file: {:?}", path.as_ref());
-        let item_json = std::fs::read_to_string(path)?;
-        let item_file: ItemFile = serde_json::from_str(&item_json)?;
-        item_file.into_iter().for_each(|item| {
-            let item_id: ItemId = item.id();
+        let mut item_file: ItemFile = read_data(path)?;
+        for loot_table in item_file.loot_table.take().unwrap_or_default() {
+            let loot_table_id = loot_table.id().to_owned();
+            if self.loot_tables.contains_key(&loot_table_id) {
+                warn!("loot table {loot_table_id:?} is defined more than once, replacing it");
+            }
+            self.loot_tables.insert(loot_table_id, loot_table);
+        }
+        for item in item_file.into_iter() {
+            let item_id = item.id();
+            if self.items.contains_key(&item_id) {
+                warn!("item {item_id:?} is defined more than once, replacing it");
+            }
             self.items.insert(item_id, item);
-        });
+        }
         Ok(())
     }
 
+    /// Replaces the item file asset `id` with `item_file`, from the pack at
+    /// the given priority, or removes it with `None`. Every item and loot
+    /// table either version defines is resolved again from all loaded files,
+    /// so reloading a file keeps the overrides of later packs, and removing
+    /// an override brings back the definition it replaced.
+    fn update_file(
+        &mut self,
+        id: AssetId<ItemFile>,
+        item_file: Option<(usize, ItemFile)>,
+        packs: &AssetPacks,
+    ) {
+        let old_file = self.files.remove(&id);
+        let mut item_ids = HashSet::new();
+        let mut loot_table_ids = HashSet::new();
+        for (_, file) in old_file.iter().chain(&item_file) {
+            item_ids.extend(file.clone().into_iter().map(|item| item.id()));
+            loot_table_ids.extend(file.loot_table.iter().flatten().map(|t| t.id().to_owned()));
+        }
+        if let Some(item_file) = item_file {
+            self.files.insert(id, item_file);
+        }
+        for item_id in item_ids {
+            self.resolve_item(item_id, packs);
+        }
+        for loot_table_id in loot_table_ids {
+            self.resolve_loot_table(loot_table_id, packs);
+        }
+    }
+
+    /// Takes `item_id` from the loaded file in the latest pack that defines
+    /// it, or removes it if none do.
+    fn resolve_item(&mut self, item_id: ItemId, packs: &AssetPacks) {
+        let mut definition: Option<(usize, AnyItem)> = None;
+        for (priority, item_file) in self.files.values() {
+            let Some(item) = item_file.clone().into_iter().find(|i| i.id() == item_id) else {
+                continue;
+            };
+            let current = definition.as_ref().map(|(current, _)| *current);
+            if packs.resolve("item", &item_id, *priority, current) {
+                definition = Some((*priority, item));
+            }
+        }
+        match definition {
+            Some((_, item)) => self.items.insert(item_id, item),
+            None => self.items.remove(&item_id),
+        };
+    }
+
+    /// Takes `loot_table_id` from the loaded file in the latest pack that
+    /// defines it, or removes it if none do.
+    fn resolve_loot_table(&mut self, loot_table_id: LootTableId, packs: &AssetPacks) {
+        let mut definition: Option<(usize, &LootTable)> = None;
+        for (priority, item_file) in self.files.values() {
+            let Some(loot_table) = item_file
+                .loot_table
+                .iter()
+                .flatten()
+                .find(|t| *t.id() == loot_table_id)
+            else {
+                continue;
+            };
+            let current = definition.map(|(current, _)| current);
+            if packs.resolve("loot table", &loot_table_id, *priority, current) {
+                definition = Some((*priority, loot_table));
+            }
+        }
+        match definition.map(|(_, loot_table)| loot_table.clone()) {
+            Some(loot_table) => self.loot_tables.insert(loot_table_id, loot_table),
+            None => self.loot_tables.remove(&loot_table_id),
+        };
+    }
+
+    /// Keeps the manager in sync with the item files loaded by the asset server.
+    pub fn handle_asset_events(
+        mut item_manager: ResMut<Self>,
+        mut asset_events: EventReader<AssetEvent<ItemFile>>,
+        item_files: Res<Assets<ItemFile>>,
+        asset_server: Res<AssetServer>,
+        packs: Res<AssetPacks>,
+    ) {
+        for event in asset_events.read() {
+            match event {
+                AssetEvent::Added { id } | AssetEvent::Modified { id } => {
+                    let Some(item_file) = item_files.get(*id) else {
+                        continue;
+                    };
+                    let priority = asset_server
+                        .get_path(*id)
+                        .map(|path| packs.priority(path.source()))
+                        .unwrap_or_default();
+                    item_manager.update_file(*id, Some((priority, item_file.clone())), &packs);
+                }
+                AssetEvent::Removed { id } => item_manager.update_file(*id, None, &packs),
+                _ => {}
+            }
+        }
+    }
+
     pub fn with_load_items<P: AsRef<Path>>(mut self, path: P) -> anyhow::Result<Self> {
         self.load_items(path)?;
         Ok(self)
     }
 
     pub fn load_folder<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<()> {
-        path.as_ref()
-            .read_dir()?
-            .filter_map(Result::ok)
-            .filter(|entry| {
-                entry
-                    .path()
-                    .extension()
-                    .map(|ext| ext == "json")
-                    .unwrap_or(false)
-            })
-            .try_for_each(|entry| self.load_items(entry.path()))
+        utils::data_files(path)?.into_iter().for_each(|path| {
+            if let Err(e) = self.load_items(&path) {
+                error!("failed to load items: {}", LoadDiagnostic::new(&path, &e));
+            }
+        });
+        Ok(())
     }
 
     pub fn with_load_folder<P: AsRef<Path>>(mut self, path: P) -> anyhow::Result<Self> {
@@ -441,25 +777,190 @@ impl ItemManager {
     }
 
     pub fn spawn(&self, item_id: ItemId) -> Option<ItemInstance> {
+        self.spawn_stack(item_id, 1)
+    }
+
+    /// Spawns a single stack of `quantity` items.
+    ///
+    /// Returns `None` if the item does not exist, or if it is not stackable and
+    /// `quantity` is not 1. Use [`ItemManager::spawn_many`] when the item may
+    /// not be stackable.
+    pub fn spawn_stack(&self, item_id: ItemId, quantity: u32) -> Option<ItemInstance> {
         let Some(item) = self.get_item(&item_id) else {
             warn!("no item with ID: {item_id:?}");
             return None;
         };
+        if quantity == 0 || (quantity > 1 && !item.is_stackable()) {
+            warn!("cannot spawn a stack of {quantity} {item_id:?}");
+            return None;
+        }
 
         Some(ItemInstance {
             instance_id: ItemInstanceId::new(),
-            item_id,
             kind: item.kind(),
+            stackable: item.is_stackable(),
+            quantity,
+            modifiers: ItemModifiers {
+                durability: item.durability(),
+                ..Default::default()
+            },
+            item_id,
         })
     }
+
+    /// Spawns `count` items, as one stack if the item is stackable or as
+    /// `count` separate instances otherwise.
+    pub fn spawn_many(&self, item_id: ItemId, count: u32) -> Vec<ItemInstance> {
+        match self.get_item(&item_id) {
+            Some(item) if item.is_stackable() => {
+                self.spawn_stack(item_id, count).into_iter().collect()
+            }
+            _ => (0..count)
+                .map_while(|_| self.spawn(item_id.clone()))
+                .collect(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::ItemInstanceId;
+    use bevy::asset::{AssetId, uuid::Uuid};
+
+    use crate::{
+        AssetPacks, Enchantment, ExperienceCurve, Inventory, ItemFile, ItemId, ItemInstance,
+        ItemInstanceId, ItemManager, RpgEntity,
+    };
+
+    fn item_manager() -> ItemManager {
+        ItemManager::new()
+            .with_load_folder(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/items"))
+            .unwrap()
+    }
 
     #[test]
     fn unique_instance_ids() {
         assert_ne!(ItemInstanceId::new(), ItemInstanceId::new())
     }
+
+    #[test]
+    fn stackable_items_merge() {
+        let item_manager = item_manager();
+        let mut inventory = Inventory::default();
+        let apple = ItemId::new("apple-pie");
+
+        let first = inventory.insert(item_manager.spawn_stack(apple.clone(), 3).unwrap());
+        let second = inventory.insert(item_manager.spawn_stack(apple.clone(), 2).unwrap());
+        assert_eq!(first, second);
+        assert_eq!(inventory.count(&apple), 5);
+
+        let split = inventory.split(&first, 2).unwrap();
+        assert_ne!(split.instance_id(), first);
+        assert_eq!(split.quantity(), 2);
+        assert_eq!(inventory.count(&apple), 3);
+
+        assert_eq!(inventory.remove_items(&apple, 10).len(), 1);
+        assert_eq!(inventory.count(&apple), 0);
+    }
+
+    #[test]
+    fn unstackable_items_do_not_merge() {
+        let item_manager = item_manager();
+        let mut inventory = Inventory::default();
+        let sword = ItemId::new("iron-sword");
+
+        for instance in item_manager.spawn_many(sword.clone(), 3) {
+            inventory.insert(instance);
+        }
+        assert_eq!(inventory.items.len(), 3);
+        assert_eq!(inventory.count(&sword), 3);
+        assert!(item_manager.spawn_stack(sword.clone(), 2).is_none());
+
+        let removed = inventory.remove_items(&sword, 2);
+        assert_eq!(removed.iter().map(|i| i.quantity()).sum::<u32>(), 2);
+        assert_eq!(inventory.count(&sword), 1);
+    }
+
+    #[test]
+    fn modifiers_change_effective_values() {
+        let item_manager = item_manager();
+        let sword_id = ItemId::new("iron-sword");
+        let sword = item_manager.get_item(&sword_id).unwrap();
+        let mut instance = item_manager.spawn(sword_id.clone()).unwrap();
+        let plain = item_manager.spawn(sword_id).unwrap();
+        assert_eq!(instance.effective_damage(sword), sword.damage());
+
+        instance.enchant(Enchantment::Damage { amount: 5 });
+        instance.set_custom_name(Some("Jake's Lucky Sword".to_string()));
+        assert_eq!(instance.effective_damage(sword), sword.damage() + 5);
+        assert_eq!(instance.name(sword), "Jake's Lucky Sword");
+        assert_eq!(plain.name(sword), sword.name());
+        assert!(!instance.stacks_with(&plain));
+
+        let json = serde_json::to_string(&instance).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ItemInstance>(&json).unwrap(),
+            instance
+        );
+    }
+
+    #[test]
+    fn inventories_round_trip_with_equipment() {
+        let item_manager = item_manager();
+        let mut player = RpgEntity::new(Some("Jake".to_string()), &ExperienceCurve::default());
+        let mut sword = item_manager.spawn(ItemId::new("iron-sword")).unwrap();
+        sword.enchant(Enchantment::Damage { amount: 5 });
+        sword.wear(3);
+        let sword_id = player.inventory.insert(sword);
+        assert!(player.equip(sword_id));
+        player.inventory.insert(
+            item_manager
+                .spawn_stack(ItemId::new("apple-pie"), 3)
+                .unwrap(),
+        );
+
+        let json = serde_json::to_string(&player).unwrap();
+        let loaded: RpgEntity = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, player);
+        assert_eq!(
+            loaded.attack_damage(&item_manager),
+            player.attack_damage(&item_manager)
+        );
+    }
+
+    #[test]
+    fn reloading_a_file_keeps_pack_overrides() {
+        let sword = |damage: u32| -> ItemFile {
+            serde_json::from_value(serde_json::json!({
+                "weapon": [{ "id": "sword", "name": "Sword", "damage": damage, "weight": 5, "value": 10 }]
+            }))
+            .unwrap()
+        };
+        let damage = |item_manager: &ItemManager| {
+            item_manager
+                .get_item(&ItemId::new("sword"))
+                .and_then(|item| item.as_weapon())
+                .map(|weapon| weapon.damage())
+        };
+        let packs = AssetPacks::default();
+        let base = AssetId::Uuid {
+            uuid: Uuid::from_u128(1),
+        };
+        let pack = AssetId::Uuid {
+            uuid: Uuid::from_u128(2),
+        };
+        let mut item_manager = ItemManager::new();
+
+        item_manager.update_file(base, Some((0, sword(5))), &packs);
+        item_manager.update_file(pack, Some((1, sword(8))), &packs);
+        assert_eq!(damage(&item_manager), Some(8));
+
+        item_manager.update_file(base, Some((0, sword(6))), &packs);
+        assert_eq!(damage(&item_manager), Some(8));
+
+        item_manager.update_file(pack, None, &packs);
+        assert_eq!(damage(&item_manager), Some(6));
+
+        item_manager.update_file(base, None, &packs);
+        assert_eq!(damage(&item_manager), None);
+    }
 }
@@ -0,0 +1,138 @@
+// bevy systems take one parameter per resource and query they use
+#![allow(clippy::too_many_arguments)]
+
+mod audio;
+mod components;
+mod coverage;
+mod diagnostics;
+mod events;
+mod experience;
+mod formats;
+mod game;
+mod graph;
+mod history;
+mod items;
+mod lint;
+mod loaders;
+mod loot;
+mod maps;
+mod packs;
+mod pixels;
+mod plugins;
+mod portraits;
+mod runner;
+mod scenes;
+mod schema;
+mod snapshots;
+mod static_commands;
+mod systems;
+mod terminal;
+mod types;
+mod typewriter;
+mod ui;
+mod utils;
+mod voices;
+
+use bevy::prelude::*;
+use bevy_rand::prelude::*;
+
+pub use audio::*;
+pub use components::*;
+pub use coverage::*;
+pub use diagnostics::*;
+pub use events::*;
+pub use experience::*;
+pub use formats::*;
+pub use game::*;
+pub use graph::*;
+pub use history::*;
+pub use items::*;
+pub use lint::*;
+pub use loaders::*;
+pub use loot::*;
+pub use maps::*;
+pub use packs::*;
+pub use pixels::*;
+pub use plugins::*;
+pub use portraits::*;
+pub use runner::*;
+pub use scenes::*;
+pub use schema::*;
+pub use snapshots::*;
+pub use static_commands::*;
+pub use systems::*;
+pub use terminal::*;
+pub use types::*;
+pub use typewriter::*;
+pub use ui::*;
+pub use utils::*;
+pub use voices::*;
+// bevy's prelude has a `Condition` trait too
+pub use scenes::Condition;
+
+/// Assets read directly from disk, by the command line tools and voices.
+#[cfg(debug_assertions)]
+pub const ASSETS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets");
+#[cfg(not(debug_assertions))]
+pub const ASSETS_PATH: &str = "assets";
+
+/// Where dialogue coverage builds up across play sessions and test runs,
+/// relative to the assets folder.
+pub const COVERAGE_PATH: &str = "../dialogue-coverage.json";
+
+#[cfg(debug_assertions)]
+pub const DEBUG: bool = true;
+#[cfg(not(debug_assertions))]
+pub const DEBUG: bool = false;
+
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Battle(Entity);
+
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct StateManager(Vec<GameState>);
+
+impl StateManager {
+    pub fn new(state: GameState) -> Self {
+        let mut stack = Vec::with_capacity(8);
+        stack.push(state);
+        Self(stack)
+    }
+
+    pub fn get(&self) -> Option<GameState> {
+        self.0.last().copied()
+    }
+
+    pub fn push(&mut self, commands: &mut Commands, state: GameState) {
+        commands.set_state(state);
+        self.0.push(state)
+    }
+
+    pub fn pop(&mut self, commands: &mut Commands) -> Option<GameState> {
+        let popped = self.0.pop();
+        commands.set_state(self.get().expect("all states popped! oh no!"));
+        popped
+    }
+}
+
+/// What the game is shown with, picked on the command line. The game runs the
+/// same under either.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Frontend {
+    /// A window drawn with egui.
+    Window,
+    /// The terminal the game was started in, drawn with ratatui. Works over
+    /// SSH.
+    Terminal,
+}
+
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameState {
+    Map,
+    Dialogue,
+    Battle,
+}
+
+pub type Rng<'w> = GlobalEntropy<'w, WyRand>;
+
+#[derive(Resource, Default)]
+pub struct DebugPlaySceneId(String);
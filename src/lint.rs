@@ -0,0 +1,438 @@
+use std::{collections::VecDeque, fmt, path::Path};
+
+use bevy::platform::collections::{HashMap, HashSet};
+
+use crate::{
+    Condition, Definitions, Dialogue, ItemId, ItemManager, LoadDiagnostic, LootTableId, MapManager,
+    NpcId, Scene, SceneId, SceneManager, SceneSectionId, StaticCommands, utils,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintKind {
+    MissingStart,
+    UnknownSection {
+        field: &'static str,
+        scene: SceneId,
+        section: SceneSectionId,
+    },
+    UnknownScene {
+        field: &'static str,
+        scene: SceneId,
+    },
+    UnreachableSection,
+    UnknownSpeaker(NpcId),
+    UnknownItem(ItemId),
+    UnknownLootTable(LootTableId),
+    UnknownQuest(String),
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintKind::MissingStart => write!(f, "no `start` section"),
+            LintKind::UnknownSection {
+                field,
+                scene,
+                section,
+            } => write!(f, "`{field}` targets unknown section `{scene}:{section}`"),
+            LintKind::UnknownScene { field, scene } => {
+                write!(f, "`{field}` targets unknown scene `{scene}`")
+            }
+            LintKind::UnreachableSection => write!(f, "section is unreachable"),
+            LintKind::UnknownSpeaker(npc_id) => {
+                write!(f, "speaker `{npc_id}` has no character definition")
+            }
+            LintKind::UnknownItem(item_id) => write!(f, "unknown item `{item_id}`"),
+            LintKind::UnknownLootTable(table_id) => write!(f, "unknown loot table `{table_id}`"),
+            LintKind::UnknownQuest(quest) => write!(f, "unknown quest `{quest}`"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintIssue {
+    pub scene: SceneId,
+    pub section: Option<SceneSectionId>,
+    pub kind: LintKind,
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.section {
+            Some(section) => write!(f, "{}:{}: {}", self.scene, section, self.kind),
+            None => write!(f, "{}: {}", self.scene, self.kind),
+        }
+    }
+}
+
+impl Scene {
+    /// Every set of commands in the scene, along with the section it is in.
+    pub(crate) fn all_commands(&self) -> Vec<(Option<&SceneSectionId>, &StaticCommands)> {
+        let mut commands: Vec<_> = self.commands.iter().map(|c| (None, c)).collect();
+        for (section, dialogue) in self.dialogue.iter() {
+            let line_commands = dialogue.lines.iter().filter_map(|l| l.commands.as_ref());
+            let response_commands = dialogue
+                .responses
+                .iter()
+                .filter_map(|r| r.commands.as_ref());
+            commands.extend(
+                dialogue
+                    .commands
+                    .iter()
+                    .chain(line_commands)
+                    .chain(response_commands)
+                    .map(|c| (Some(section), c)),
+            );
+        }
+        commands
+    }
+}
+
+impl Definitions {
+    fn quest_ids(&self) -> impl Iterator<Item = &String> {
+        self.quests
+            .as_ref()
+            .and_then(|quests| quests.as_object())
+            .into_iter()
+            .flat_map(|quests| quests.keys())
+    }
+}
+
+impl Dialogue {
+    /// Sections this dialogue can move on to, and the field that links them.
+    fn links(&self) -> Vec<(&'static str, &SceneSectionId)> {
+        let mut links = Vec::new();
+        for response in &self.responses {
+            links.extend(response.link.iter().map(|link| ("lnk", link)));
+            if let Some(skill_check) = &response.skill_check {
+                links.push(("lnk-fail", &skill_check.link_fail));
+                links.extend(
+                    skill_check
+                        .link_crit_fail
+                        .iter()
+                        .map(|link| ("lnk-crit-fail", link)),
+                );
+            }
+        }
+        links.extend(self.continue_to.iter().map(|link| ("cont", link)));
+        links
+    }
+}
+
+fn condition_items(condition: &Condition, items: &mut Vec<ItemId>) {
+    match condition {
+        Condition::HasItem { item, .. } => items.push(item.to_owned()),
+        Condition::Any { conditions } | Condition::Not { conditions } => {
+            conditions.iter().for_each(|c| condition_items(c, items))
+        }
+        Condition::VarEquals { .. } | Condition::QuestStage => {}
+    }
+}
+
+impl SceneManager {
+    /// Checks every loaded scene for broken links, unreachable sections and
+    /// references to things that don't exist. Quests can be defined by scenes
+    /// or maps, and `global_characters` can speak in any scene.
+    pub fn lint(
+        &self,
+        item_manager: &ItemManager,
+        map_manager: &MapManager,
+        global_characters: &[NpcId],
+    ) -> Vec<LintIssue> {
+        let mut issues = Vec::new();
+
+        // scene entry points can be changed from any scene
+        let mut entry_points: HashMap<&SceneId, HashSet<&SceneSectionId>> = HashMap::default();
+        for (scene, section) in self.entries.iter() {
+            entry_points.entry(scene).or_default().insert(section);
+        }
+        for scene in self.scenes.values() {
+            for (section, commands) in scene.all_commands() {
+                for (target_scene, target_section) in commands.scene_entry.iter().flatten() {
+                    entry_points
+                        .entry(target_scene)
+                        .or_default()
+                        .insert(target_section);
+                    let issue = match self.scenes.get(target_scene) {
+                        None => LintKind::UnknownScene {
+                            field: "scene-entry",
+                            scene: target_scene.to_owned(),
+                        },
+                        Some(target) if !target.dialogue.contains_key(target_section) => {
+                            LintKind::UnknownSection {
+                                field: "scene-entry",
+                                scene: target_scene.to_owned(),
+                                section: target_section.to_owned(),
+                            }
+                        }
+                        Some(_) => continue,
+                    };
+                    issues.push(LintIssue {
+                        scene: scene.id().to_owned(),
+                        section: section.cloned(),
+                        kind: issue,
+                    });
+                }
+            }
+        }
+
+        let quests: HashSet<&str> = self
+            .scenes
+            .values()
+            .map(|scene| &scene.definitions)
+            .chain(map_manager.maps.values().map(|map| &map.definitions))
+            .flat_map(Definitions::quest_ids)
+            .map(String::as_str)
+            .collect();
+
+        for scene in self.scenes.values() {
+            let issue = |section: Option<&SceneSectionId>, kind| LintIssue {
+                scene: scene.id().to_owned(),
+                section: section.cloned(),
+                kind,
+            };
+
+            let start = SceneSectionId::default();
+            if !scene.dialogue.contains_key(&start) {
+                issues.push(issue(None, LintKind::MissingStart));
+            }
+
+            // links and reachability
+            let mut reachable: HashSet<&SceneSectionId> = HashSet::default();
+            let mut queue: VecDeque<&SceneSectionId> = entry_points
+                .get(scene.id())
+                .into_iter()
+                .flatten()
+                .copied()
+                .chain(std::iter::once(&start))
+                .collect();
+            while let Some(section) = queue.pop_front() {
+                let Some(dialogue) = scene.dialogue.get(section) else {
+                    continue;
+                };
+                if reachable.insert(section) {
+                    queue.extend(dialogue.links().into_iter().map(|(_, link)| link));
+                }
+            }
+            for (section, dialogue) in scene.dialogue.iter() {
+                if !reachable.contains(section) {
+                    issues.push(issue(Some(section), LintKind::UnreachableSection));
+                }
+                for (field, link) in dialogue.links() {
+                    if !scene.dialogue.contains_key(link) {
+                        let kind = LintKind::UnknownSection {
+                            field,
+                            scene: scene.id().to_owned(),
+                            section: link.to_owned(),
+                        };
+                        issues.push(issue(Some(section), kind));
+                    }
+                }
+
+                // speakers
+                let characters = scene.definitions.characters.as_ref();
+                let unknown_speakers: HashSet<&NpcId> = dialogue
+                    .lines
+                    .iter()
+                    .map(|line| &line.from)
+                    .filter(|from| !global_characters.contains(*from))
+                    .filter(|from| characters.is_none_or(|c| !c.contains_key(*from)))
+                    .collect();
+                for from in unknown_speakers {
+                    issues.push(issue(
+                        Some(section),
+                        LintKind::UnknownSpeaker(from.to_owned()),
+                    ));
+                }
+
+                // items in conditions
+                let mut items = Vec::new();
+                for response in &dialogue.responses {
+                    response
+                        .conditions
+                        .iter()
+                        .for_each(|c| condition_items(c, &mut items));
+                }
+                for item in items {
+                    if item_manager.get_item(&item).is_none() {
+                        issues.push(issue(Some(section), LintKind::UnknownItem(item)));
+                    }
+                }
+            }
+
+            // items, loot and quests in commands
+            for (section, commands) in scene.all_commands() {
+                let items = commands
+                    .reward_item
+                    .iter()
+                    .map(|reward| reward.item())
+                    .chain(commands.remove_item.iter().map(|remove| &remove.item));
+                for item in items {
+                    if item_manager.get_item(item).is_none() {
+                        issues.push(issue(section, LintKind::UnknownItem(item.to_owned())));
+                    }
+                }
+                if let Some(table) = &commands.reward_loot
+                    && item_manager.get_loot_table(table).is_none()
+                {
+                    issues.push(issue(section, LintKind::UnknownLootTable(table.to_owned())));
+                }
+                let quest_refs = commands
+                    .set_quest_stage
+                    .iter()
+                    .filter_map(|stage| stage.get("quest"))
+                    .chain(commands.complete_quest.iter())
+                    .filter_map(|quest| quest.as_str());
+                for quest in quest_refs {
+                    if !quests.contains(quest) {
+                        issues.push(issue(section, LintKind::UnknownQuest(quest.to_string())));
+                    }
+                }
+            }
+        }
+
+        issues.sort_by_key(|issue| issue.to_string());
+        issues
+    }
+}
+
+/// Loads every data file in `folder`, collecting the failures.
+fn load_each(folder: &Path, mut load: impl FnMut(&Path) -> anyhow::Result<()>) -> Vec<String> {
+    let paths = match utils::data_files(folder) {
+        Ok(paths) => paths,
+        Err(e) => return vec![format!("{}: {e}", folder.display())],
+    };
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            load(&path)
+                .err()
+                .map(|e| LoadDiagnostic::new(&path, &e).to_string())
+        })
+        .collect()
+}
+
+/// Loads and lints every asset under `assets_path`, printing each problem.
+/// `global_characters` are the game manifest's characters, which exist in
+/// every scene. Returns the number of problems found.
+pub fn check_assets(assets_path: &Path, global_characters: &[NpcId]) -> usize {
+    let mut item_manager = ItemManager::new();
+    let mut map_manager = MapManager::new();
+    let mut scene_manager = SceneManager::new();
+    let mut problems = load_each(&assets_path.join("items"), |path| {
+        item_manager.load_items(path)
+    });
+    problems.extend(load_each(&assets_path.join("maps"), |path| {
+        map_manager.load_map(path)
+    }));
+    problems.extend(load_each(&assets_path.join("scenes"), |path| {
+        scene_manager.load_scene(path)
+    }));
+    problems.extend(
+        scene_manager
+            .lint(&item_manager, &map_manager, global_characters)
+            .iter()
+            .map(LintIssue::to_string),
+    );
+
+    for problem in &problems {
+        eprintln!("{problem}");
+    }
+    println!(
+        "checked {} scenes, {} maps and {} items: {} problems",
+        scene_manager.scenes.len(),
+        map_manager.maps.len(),
+        item_manager.items.len(),
+        problems.len()
+    );
+    problems.len()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        GameManifest, ItemManager, LintKind, MapManager, NpcId, Scene, SceneManager, SceneSectionId,
+    };
+
+    fn lint(scene_json: &str) -> Vec<LintKind> {
+        let item_manager = ItemManager::new()
+            .with_load_folder(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/items"))
+            .unwrap();
+        let map_manager = MapManager::new()
+            .with_load_folder(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/maps"))
+            .unwrap();
+        let global_characters: Vec<NpcId> =
+            GameManifest::default().characters.into_keys().collect();
+        let scene: Scene = serde_json::from_str(scene_json).unwrap();
+        let mut scene_manager = SceneManager::new();
+        scene_manager.scenes.insert(scene.id().to_owned(), scene);
+        scene_manager
+            .lint(&item_manager, &map_manager, &global_characters)
+            .into_iter()
+            .map(|issue| issue.kind)
+            .collect()
+    }
+
+    #[test]
+    fn reports_broken_scenes() {
+        let issues = lint(
+            r#"{
+                "id": "broken",
+                "characters": { "mike": { "name": "Mike" } },
+                "dialogue": {
+                    "start": {
+                        "lines": [{ "from": "mike", "text": "Hi." }],
+                        "responses": [
+                            { "text": "Go", "lnk": "nowhere" },
+                            {
+                                "text": "Pie?",
+                                "cond": [{ "type": "has-item", "item": "moon-pie" }]
+                            }
+                        ]
+                    },
+                    "lonely": {
+                        "lines": [{ "from": "nobody", "text": "Hello?" }]
+                    }
+                }
+            }"#,
+        );
+        assert!(issues.contains(&LintKind::UnreachableSection));
+        assert!(issues.iter().any(|kind| matches!(
+            kind,
+            LintKind::UnknownSection { field: "lnk", section, .. }
+                if *section == SceneSectionId("nowhere".into())
+        )));
+        assert!(
+            issues
+                .iter()
+                .any(|kind| matches!(kind, LintKind::UnknownSpeaker(npc) if npc.0 == "nobody"))
+        );
+        assert!(
+            issues
+                .iter()
+                .any(|kind| matches!(kind, LintKind::UnknownItem(item) if item.0 == "moon-pie"))
+        );
+        assert!(!issues.contains(&LintKind::MissingStart));
+    }
+
+    #[test]
+    fn clean_scenes_have_no_issues() {
+        let issues = lint(
+            r#"{
+                "id": "clean",
+                "dialogue": {
+                    "start": {
+                        "lines": [{ "from": "narrator", "text": "A road." }],
+                        "cont": "end"
+                    },
+                    "end": {
+                        "lines": [{ "from": "narrator", "text": "The end." }],
+                        "reward-item": "apple-pie",
+                        "complete-quest": "lost-treasure"
+                    }
+                }
+            }"#,
+        );
+        assert_eq!(issues, []);
+    }
+}
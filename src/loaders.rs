@@ -0,0 +1,138 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    asset::{
+        AssetLoader, AssetPath, LoadContext, LoadedFolder, RecursiveDependencyLoadState, io::Reader,
+    },
+    ecs::system::ScheduleSystem,
+    prelude::*,
+};
+use serde::de::DeserializeOwned;
+
+use crate::{AssetPacks, DataFormat, FormatError, clear_load_diagnostics, report_load_failures};
+
+/// Loads scenes, item files and maps, in any [`DataFormat`].
+pub struct DataLoader<A> {
+    extensions: &'static [&'static str],
+    asset: PhantomData<fn() -> A>,
+}
+
+impl<A> DataLoader<A> {
+    /// Creates a loader for files ending in `extensions`, e.g. `scene.json`.
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            asset: PhantomData,
+        }
+    }
+}
+
+impl<A: Asset + DeserializeOwned> AssetLoader for DataLoader<A> {
+    type Asset = A;
+    type Settings = ();
+    type Error = FormatError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let format = DataFormat::from_path(load_context.path())?;
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        format.parse(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
+
+/// Folders of game data in each asset pack, loaded at startup. Filled in by
+/// [`register_data_asset`].
+#[derive(Resource, Debug, Default)]
+pub struct DataFolderNames(Vec<&'static str>);
+
+/// Systems that copy loaded data assets into their managers. Anything that
+/// reads the managers once data has loaded runs after them.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SyncData;
+
+/// Loads `A` from `folder` in every asset pack, from files ending in
+/// `extensions`. `sync` keeps its manager up to date with the loaded assets.
+pub fn register_data_asset<A: Asset + DeserializeOwned, M>(
+    app: &mut App,
+    folder: &'static str,
+    extensions: &'static [&'static str],
+    sync: impl IntoScheduleConfigs<ScheduleSystem, M>,
+) {
+    app.init_asset::<A>()
+        .register_asset_loader(DataLoader::<A>::new(extensions))
+        .init_resource::<DataFolderNames>()
+        // asset events are sent in `PostUpdate`
+        .add_systems(Last, sync.in_set(SyncData))
+        .add_systems(
+            Update,
+            (report_load_failures::<A>, clear_load_diagnostics::<A>),
+        );
+    app.world_mut()
+        .resource_mut::<DataFolderNames>()
+        .0
+        .push(folder);
+}
+
+/// Handles to the data folders. Holding them keeps every file in them loaded.
+#[derive(Resource, Debug)]
+pub struct DataFolders {
+    folders: Vec<Handle<LoadedFolder>>,
+    loaded: bool,
+}
+
+/// Sent once every data folder has been loaded and the managers are populated.
+#[derive(Event, Debug)]
+pub struct DataLoadedEvent;
+
+pub fn load_data_folders(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    packs: Res<AssetPacks>,
+    folder_names: Res<DataFolderNames>,
+) {
+    let mut folders = Vec::new();
+    for (i, pack) in packs.iter().enumerate() {
+        // packs only need the folders they add to
+        for folder in folder_names.0.iter().filter(|f| pack.path.join(f).is_dir()) {
+            let path = AssetPath::from(*folder).with_source(packs.source(i));
+            folders.push(asset_server.load_folder(path));
+        }
+    }
+    commands.insert_resource(DataFolders {
+        folders,
+        loaded: false,
+    });
+}
+
+/// Sends [`DataLoadedEvent`] when every data folder has finished loading.
+/// Files that failed to load are reported by [`report_load_failures`], so they
+/// don't hold up the game.
+pub fn check_data_loaded(
+    mut data_folders: ResMut<DataFolders>,
+    asset_server: Res<AssetServer>,
+    mut data_loaded_event: EventWriter<DataLoadedEvent>,
+) {
+    if data_folders.loaded {
+        return;
+    }
+    let finished = data_folders.folders.iter().all(|folder| {
+        matches!(
+            asset_server.recursive_dependency_load_state(folder),
+            RecursiveDependencyLoadState::Loaded | RecursiveDependencyLoadState::Failed(_)
+        )
+    });
+    if finished {
+        info!("finished loading game data");
+        data_folders.loaded = true;
+        data_loaded_event.write(DataLoadedEvent);
+    }
+}
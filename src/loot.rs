@@ -0,0 +1,309 @@
+use bevy::log::warn;
+use rand_core::RngCore;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::{ItemId, ItemInstance, ItemManager};
+
+const MAX_LOOT_TABLE_DEPTH: usize = 8;
+
+#[derive(
+    Deserialize,
+    JsonSchema,
+    Debug,
+    Hash,
+    Clone,
+    PartialEq,
+    Eq,
+    derive_more::From,
+    derive_more::Display,
+)]
+pub struct LootTableId(pub String);
+
+impl LootTableId {
+    pub fn new(s: &str) -> Self {
+        Self(s.to_string())
+    }
+}
+
+#[derive(
+    Deserialize, JsonSchema, Debug, Default, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LootTable {
+    id: LootTableId,
+    rolls: Option<u32>,
+    entries: Vec<LootEntry>,
+}
+
+impl LootTable {
+    pub fn id(&self) -> &LootTableId {
+        &self.id
+    }
+
+    pub fn rolls(&self) -> u32 {
+        self.rolls.unwrap_or(1)
+    }
+
+    pub fn entries(&self) -> &[LootEntry] {
+        &self.entries
+    }
+
+    /// Picks a weighted entry, ignoring entries rarer than `max_rarity`.
+    fn pick<R: RngCore + ?Sized>(
+        &self,
+        max_rarity: Option<Rarity>,
+        rng: &mut R,
+    ) -> Option<&LootEntry> {
+        let allowed = |entry: &&LootEntry| max_rarity.is_none_or(|max| entry.rarity <= max);
+        let total: u64 = self
+            .entries
+            .iter()
+            .filter(allowed)
+            .map(|entry| entry.weight() as u64)
+            .sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = rng.next_u64() % total;
+        self.entries.iter().filter(allowed).find(|entry| {
+            let weight = entry.weight() as u64;
+            if roll < weight {
+                true
+            } else {
+                roll -= weight;
+                false
+            }
+        })
+    }
+}
+
+/// A single weighted drop. Entries with neither `item` nor `table` drop nothing.
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(
+    rename_all = "kebab-case",
+    deny_unknown_fields,
+    try_from = "LootEntryFields"
+)]
+pub struct LootEntry {
+    item: Option<ItemId>,
+    table: Option<LootTableId>,
+    weight: Option<u32>,
+    #[serde(default)]
+    rarity: Rarity,
+    min: Option<u32>,
+    max: Option<u32>,
+}
+
+/// A [`LootEntry`] as written, before its counts are checked.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct LootEntryFields {
+    item: Option<ItemId>,
+    table: Option<LootTableId>,
+    weight: Option<u32>,
+    #[serde(default)]
+    rarity: Rarity,
+    min: Option<u32>,
+    max: Option<u32>,
+}
+
+impl TryFrom<LootEntryFields> for LootEntry {
+    type Error = String;
+
+    fn try_from(fields: LootEntryFields) -> Result<Self, Self::Error> {
+        let LootEntryFields {
+            item,
+            table,
+            weight,
+            rarity,
+            min,
+            max,
+        } = fields;
+        let min_count = min.unwrap_or(1);
+        if let Some(max) = max
+            && max < min_count
+        {
+            return Err(format!("max ({max}) is below min ({min_count})"));
+        }
+        Ok(Self {
+            item,
+            table,
+            weight,
+            rarity,
+            min,
+            max,
+        })
+    }
+}
+
+impl LootEntry {
+    pub fn item(&self) -> Option<&ItemId> {
+        self.item.as_ref()
+    }
+
+    pub fn table(&self) -> Option<&LootTableId> {
+        self.table.as_ref()
+    }
+
+    pub fn weight(&self) -> u32 {
+        self.weight.unwrap_or(1)
+    }
+
+    pub fn rarity(&self) -> Rarity {
+        self.rarity
+    }
+
+    pub fn min(&self) -> u32 {
+        self.min.unwrap_or(1)
+    }
+
+    pub fn max(&self) -> u32 {
+        self.max.unwrap_or(self.min())
+    }
+
+    fn roll_count<R: RngCore + ?Sized>(&self, rng: &mut R) -> u32 {
+        let (min, max) = (self.min() as u64, self.max() as u64);
+        // in u64, so the full range of u32 counts doesn't overflow
+        (min + rng.next_u64() % (max - min + 1)) as u32
+    }
+}
+
+impl ItemManager {
+    pub fn get_loot_table(&self, id: &LootTableId) -> Option<&LootTable> {
+        self.loot_tables.get(id)
+    }
+
+    /// Rolls a loot table, spawning the dropped items.
+    ///
+    /// Entries rarer than `max_rarity` are never picked. Rolls are fully
+    /// determined by `rng`, so a seeded RNG always produces the same loot.
+    pub fn roll_loot<R: RngCore + ?Sized>(
+        &self,
+        table_id: &LootTableId,
+        max_rarity: Option<Rarity>,
+        rng: &mut R,
+    ) -> Vec<ItemInstance> {
+        let mut loot = Vec::new();
+        self.roll_loot_into(table_id, max_rarity, rng, 0, &mut loot);
+        loot
+    }
+
+    fn roll_loot_into<R: RngCore + ?Sized>(
+        &self,
+        table_id: &LootTableId,
+        max_rarity: Option<Rarity>,
+        rng: &mut R,
+        depth: usize,
+        loot: &mut Vec<ItemInstance>,
+    ) {
+        if depth > MAX_LOOT_TABLE_DEPTH {
+            warn!("loot table nesting is too deep, stopping at: {table_id:?}");
+            return;
+        }
+        let Some(table) = self.get_loot_table(table_id) else {
+            warn!("no loot table with ID: {table_id:?}");
+            return;
+        };
+
+        for _ in 0..table.rolls() {
+            let Some(entry) = table.pick(max_rarity, rng) else {
+                continue;
+            };
+            let count = entry.roll_count(rng);
+            if let Some(item_id) = entry.item() {
+                loot.extend(self.spawn_many(item_id.to_owned(), count));
+            } else if let Some(nested_table_id) = entry.table() {
+                for _ in 0..count {
+                    self.roll_loot_into(nested_table_id, max_rarity, rng, depth + 1, loot);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy_rand::prelude::WyRand;
+    use rand_core::SeedableRng;
+
+    use crate::{ItemId, ItemManager, LootEntry, LootTableId, Rarity};
+
+    fn item_manager() -> ItemManager {
+        ItemManager::new()
+            .with_load_folder(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/items"))
+            .unwrap()
+    }
+
+    fn roll(
+        item_manager: &ItemManager,
+        seed: u64,
+        max_rarity: Option<Rarity>,
+    ) -> Vec<(ItemId, u32)> {
+        let mut rng = WyRand::seed_from_u64(seed);
+        item_manager
+            .roll_loot(&LootTableId::new("forest-treasure"), max_rarity, &mut rng)
+            .into_iter()
+            .map(|instance| (instance.item_id().to_owned(), instance.quantity()))
+            .collect()
+    }
+
+    #[test]
+    fn seeded_rolls_are_reproducible() {
+        let item_manager = item_manager();
+        for seed in 0..32 {
+            let loot = roll(&item_manager, seed, None);
+            assert!(!loot.is_empty());
+            assert_eq!(loot, roll(&item_manager, seed, None));
+        }
+    }
+
+    #[test]
+    fn counts_cover_the_full_range() {
+        let entry: LootEntry = serde_json::from_value(
+            serde_json::json!({ "item": "coin", "min": 0, "max": u32::MAX }),
+        )
+        .unwrap();
+        let mut rng = WyRand::seed_from_u64(0);
+        for _ in 0..32 {
+            entry.roll_count(&mut rng);
+        }
+    }
+
+    #[test]
+    fn max_below_min_is_rejected() {
+        let error = serde_json::from_value::<LootEntry>(
+            serde_json::json!({ "item": "coin", "min": 3, "max": 2 }),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("below min"));
+        assert!(
+            serde_json::from_value::<LootEntry>(serde_json::json!({ "item": "coin", "max": 0 }))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn rarity_cap_excludes_rare_entries() {
+        let item_manager = item_manager();
+        for seed in 0..32 {
+            assert!(
+                roll(&item_manager, seed, Some(Rarity::Common))
+                    .iter()
+                    .all(|(item_id, _)| item_id.0 != "dragonbone-sword")
+            );
+        }
+    }
+}
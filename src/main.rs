@@ -1,202 +1,289 @@
-#![allow(unused)]
-#![warn(unused_mut, unused_variables, unused_imports)]
+use std::path::{Path, PathBuf};
 
-mod components;
-mod events;
-mod items;
-mod maps;
-mod pixels;
-mod scenes;
-mod static_commands;
-mod systems;
-mod types;
-mod ui;
-mod utils;
-
-use std::path::Path;
-
-use bevy::prelude::*;
-use bevy_egui::{EguiContextPass, EguiPlugin};
+use bevy::{app::ScheduleRunnerPlugin, prelude::*, state::app::StatesPlugin};
+use bevy_egui::EguiPlugin;
 use bevy_rand::prelude::*;
+use bevy_ratatui::RatatuiPlugins;
+use gizmo::*;
 
-pub use components::*;
-pub use events::*;
-pub use items::*;
-pub use maps::*;
-pub use pixels::*;
-pub use scenes::*;
-pub use static_commands::*;
-pub use systems::*;
-pub use types::*;
-pub use ui::*;
-pub use utils::*;
-
-// TODO: use bevy asset loader somehow
-#[cfg(debug_assertions)]
-const ASSETS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets");
-#[cfg(not(debug_assertions))]
-const ASSETS_PATH: &str = "assets";
-
-#[cfg(debug_assertions)]
-pub const DEBUG: bool = true;
-#[cfg(not(debug_assertions))]
-pub const DEBUG: bool = false;
-
-#[derive(Resource, Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Battle(Entity);
-
-#[derive(Resource, Debug, Clone, PartialEq, Eq, Hash, Default)]
-pub struct StateManager(Vec<GameState>);
+/// Every flag gizmo understands, with its value and what it does, for
+/// `--help` and for catching typos.
+const FLAGS: &[(&str, &str, &str)] = &[
+    ("--help", "", "show this help"),
+    ("--assets", "<dir>", "the base game's asset folder"),
+    ("--game", "<manifest>", "the game manifest, e.g. game.json"),
+    (
+        "--pack",
+        "<dir>",
+        "an asset pack loaded on top, may be repeated",
+    ),
+    ("--save", "<slot>", "start from a save slot, e.g. quicksave"),
+    ("--scene", "<id>[:<section>]", "start in a scene"),
+    ("--map", "<id>", "start on a map"),
+    ("--pos", "x,y", "where to start on the map"),
+    (
+        "--var",
+        "<key>=<value>",
+        "set a scene variable, may be repeated",
+    ),
+    ("--seed", "<number>", "seed the random number generator"),
+    ("--tui", "", "play in the terminal instead of a window"),
+    ("--check-assets", "", "check every asset and exit"),
+    (
+        "--bless-snapshots",
+        "",
+        "rewrite the scene snapshots and exit",
+    ),
+    (
+        "--convert",
+        "<input> <output>",
+        "convert a data file to another format",
+    ),
+    (
+        "--coverage-report",
+        "[<file>]",
+        "report which dialogue has been played",
+    ),
+    (
+        "--export-schemas",
+        "[<dir>]",
+        "write the JSON schemas of data files",
+    ),
+    ("--export-graph", "<scene-id>", "print the graph of a scene"),
+    (
+        "--graph-format",
+        "dot|mermaid",
+        "the format --export-graph prints",
+    ),
+];
 
-impl StateManager {
-    fn new(state: GameState) -> Self {
-        let mut stack = Vec::with_capacity(8);
-        stack.push(state);
-        Self(stack)
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        print!("{}", usage());
+        return Ok(());
     }
-
-    pub fn get(&self) -> Option<GameState> {
-        self.0.last().copied()
+    if let Some(flag) = args
+        .iter()
+        .skip(1)
+        .find(|arg| arg.starts_with("--") && !FLAGS.iter().any(|(known, ..)| known == arg))
+    {
+        anyhow::bail!("unknown flag {flag}\n\n{}", usage());
     }
-
-    pub fn push(&mut self, commands: &mut Commands, state: GameState) {
-        commands.set_state(state);
-        self.0.push(state)
+    let assets_path = match flag_value(&args, "--assets", "<dir>")? {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(ASSETS_PATH),
+    };
+    let mut game = match flag_value(&args, "--game", "<manifest>, e.g. game.json")? {
+        Some(path) => GameManifest::load(path)?,
+        None => {
+            let path = Path::new(ASSETS_PATH).join("..").join(GAME_MANIFEST_FILE);
+            if path.exists() {
+                GameManifest::load(path)?
+            } else {
+                GameManifest::default()
+            }
+        }
+    };
+    if args.iter().any(|arg| arg == "--check-assets") {
+        let global_characters: Vec<NpcId> = game.characters.keys().cloned().collect();
+        let problems = check_assets(&assets_path, &global_characters);
+        if problems > 0 {
+            anyhow::bail!("found {problems} problems in assets");
+        }
+        return Ok(());
+    }
+    if args.iter().any(|arg| arg == "--bless-snapshots") {
+        let snapshots_path = assets_path.join("../snapshots/scenes");
+        let mut coverage = DialogueCoverage::default();
+        check_snapshots(&assets_path, &snapshots_path, true, &mut coverage)?;
+        coverage.merge_into_file(assets_path.join(COVERAGE_PATH))?;
+        println!("blessed scene snapshots in: {}", snapshots_path.display());
+        return Ok(());
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--convert") {
+        let (Some(input), Some(output)) = (value_after(&args, i), value_after(&args, i + 1)) else {
+            anyhow::bail!(
+                "usage: --convert <input> <output>, e.g. mike.scene.json mike.scene.yaml"
+            );
+        };
+        convert(input, output)?;
+        println!("converted {input} to {output}");
+        return Ok(());
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--coverage-report") {
+        let coverage_path = match value_after(&args, i) {
+            Some(path) => Path::new(path).to_path_buf(),
+            None => assets_path.join(COVERAGE_PATH),
+        };
+        print!("{}", coverage_report(&assets_path, &coverage_path)?);
+        return Ok(());
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--export-schemas") {
+        let dir = match value_after(&args, i) {
+            Some(dir) => PathBuf::from(dir),
+            None => assets_path.join("../schemas"),
+        };
+        export_schemas(&dir)?;
+        println!("exported schemas to: {}", dir.display());
+        return Ok(());
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--export-graph") {
+        let Some(scene_id) = value_after(&args, i) else {
+            anyhow::bail!("usage: --export-graph <scene-id> [--graph-format dot|mermaid]");
+        };
+        let format = match args.iter().position(|arg| arg == "--graph-format") {
+            Some(i) => value_after(&args, i).unwrap_or_default().parse()?,
+            None => GraphFormat::default(),
+        };
+        print!(
+            "{}",
+            export_graph(&assets_path, &SceneId::new(scene_id), format)?
+        );
+        return Ok(());
     }
 
-    pub fn pop(&mut self, commands: &mut Commands) -> Option<GameState> {
-        let popped = self.0.pop();
-        commands.set_state(self.get().expect("all states popped! oh no!"));
-        popped
+    if args.iter().any(|arg| arg == "--assets") {
+        game.assets.base = assets_path.clone();
+    }
+    // a save, then anything given on the command line, goes on top of where
+    // the game would start
+    if let Some(slot) = flag_value(&args, "--save", "<slot>, e.g. quicksave")? {
+        game.start
+            .merge(GameStart::load_save(&game.assets.base, slot)?);
+    }
+    game.start.merge(start_from_args(&args)?);
+    // a position only means something on the map it was given for, which the
+    // save may have chosen
+    if game.start.position.is_some() && game.start.map.is_none() {
+        anyhow::bail!("--pos needs a map, usage: --map <id> --pos x,y");
     }
-}
 
-#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum GameState {
-    Map,
-    Dialogue,
-    Battle,
-}
+    // packs after the ones the game loads, in load order
+    let pack_paths: Vec<PathBuf> = flag_values(&args, "--pack", "<dir>")?
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    let packs = game.asset_packs(&pack_paths)?;
+    let problems = packs.check();
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("{problem}");
+        }
+        anyhow::bail!("found {} problems in asset packs", problems.len());
+    }
 
-pub type Rng<'w> = GlobalEntropy<'w, WyRand>;
+    let frontend = if args.iter().any(|arg| arg == "--tui") {
+        Frontend::Terminal
+    } else {
+        Frontend::Window
+    };
 
-fn main() -> anyhow::Result<()> {
-    let state_manager = StateManager::new(GameState::Map);
     let mut app = App::new();
-    // TODO: make Manager structs support hot-reloading
-    app.add_plugins((
-        DefaultPlugins,
-        EguiPlugin {
-            enable_multipass_for_primary_context: true,
-        },
-        EntropyPlugin::<WyRand>::default(),
-    ))
-    .insert_state(state_manager.get().expect("state exists."))
-    .insert_resource(state_manager)
-    .insert_resource(ItemManager::new())
-    .insert_resource(MapManager::new())
-    .insert_resource(SceneManager::new())
-    .add_systems(Startup, (setup, setup_pixel_buffer))
-    .add_systems(Update, exit_on_esc)
-    .add_systems(Update, draw_random_pixels.run_if(in_state(GameState::Map)));
-
-    register_events(&mut app);
-    register_ui(&mut app);
+    // asset sources have to exist before the asset plugin is added
+    packs.register_sources(&mut app);
+    // reload items, maps and scenes when their files change
+    let asset_plugin = AssetPlugin {
+        file_path: game.assets.base.to_string_lossy().into_owned(),
+        watch_for_changes_override: Some(true),
+        ..default()
+    };
+    match frontend {
+        Frontend::Window => app.add_plugins((
+            DefaultPlugins.set(asset_plugin),
+            EguiPlugin {
+                enable_multipass_for_primary_context: true,
+            },
+        )),
+        // no window, renderer, audio or logging, which would draw over the UI
+        Frontend::Terminal => app.add_plugins((
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(TERMINAL_FRAME_TIME)),
+            asset_plugin,
+            StatesPlugin,
+            RatatuiPlugins::default(),
+        )),
+    };
+    let entropy_plugin = match flag_value(&args, "--seed", "<number>")? {
+        Some(seed) => EntropyPlugin::<WyRand>::with_seed(seed.parse::<u64>()?.to_le_bytes()),
+        None => EntropyPlugin::<WyRand>::default(),
+    };
+    app.add_plugins(entropy_plugin)
+        .insert_resource(packs)
+        .add_plugins(GizmoPlugin {
+            settings: GizmoSettings {
+                frontend: Some(frontend),
+                game,
+                ..default()
+            },
+        });
 
     app.run();
 
     Ok(())
 }
 
-fn register_events(app: &mut App) {
-    app.add_event::<AttackEvent>()
-        .add_event::<DamageEvent>()
-        .add_event::<DeathEvent>()
-        .add_event::<PlaySceneEvent>()
-        .add_event::<EndSceneEvent>()
-        .add_event::<StaticCommandsEvent>()
-        .add_event::<StartBattleEvent>()
-        .add_event::<EndBattleEvent>()
-        .add_event::<SpawnNpcEvent>()
-        .add_event::<UpdateNpcEvent>()
-        .add_systems(
-            PostUpdate,
-            (
-                // RPG events
-                AttackEvent::handler,
-                DamageEvent::handler,
-                DeathEvent::handler,
-                // scene events
-                PlaySceneEvent::handler,
-                StaticCommandsEvent::handler,
-                EndSceneEvent::handler,
-                // battle events
-                StartBattleEvent::handler,
-                EndBattleEvent::handler,
-                // meta events
-                SpawnNpcEvent::handler,
-                UpdateNpcEvent::handler,
-            ),
-        );
-}
-
-fn register_ui(app: &mut App) {
-    app.add_systems(EguiContextPass, debug_ui.run_if(|| DEBUG))
-        .add_systems(EguiContextPass, map_ui.run_if(in_state(GameState::Map)))
-        .add_systems(
-            EguiContextPass,
-            (dialogue_ui, dialogue_ui_input).run_if(in_state(GameState::Dialogue)),
-        )
-        .add_systems(
-            EguiContextPass,
-            battle_ui.run_if(in_state(GameState::Battle)),
-        );
+/// Where the game starts, from `--scene <id>[:<section>]`, `--map <id>`,
+/// `--pos x,y` and any number of `--var key=value`.
+fn start_from_args(args: &[String]) -> anyhow::Result<GameStart> {
+    let mut start = GameStart::default();
+    if let Some(scene) = flag_value(args, "--scene", "<id>[:<section>]")? {
+        let (scene_id, section_id) = match scene.split_once(':') {
+            Some((scene_id, section_id)) => (scene_id, Some(section_id)),
+            None => (scene, None),
+        };
+        start.scene = Some(SceneId::new(scene_id));
+        start.section = section_id.map(|section_id| SceneSectionId(section_id.to_string()));
+    }
+    if let Some(map) = flag_value(args, "--map", "<id> [--pos x,y]")? {
+        start.map = Some(MapId::new(map));
+    }
+    if let Some(position) = flag_value(args, "--pos", "x,y")? {
+        start.position = Some(position.parse()?);
+    }
+    for variable in flag_values(args, "--var", "<key>=<value>")? {
+        let Some((key, value)) = variable.split_once('=') else {
+            anyhow::bail!("usage: --var <key>=<value>, got {variable:?}");
+        };
+        start.variables.insert(key.to_string(), value.to_string());
+    }
+    Ok(start)
 }
 
-fn setup(
-    mut commands: Commands,
-    // server: ResMut<AssetServer>,
-    mut item_manager: ResMut<ItemManager>,
-    mut map_manager: ResMut<MapManager>,
-    mut scene_manager: ResMut<SceneManager>,
-    mut state_manager: ResMut<StateManager>,
-    npc_query: Query<&Npc>,
-) {
-    commands.spawn(Camera2d);
-    // value for text input for selecting scenes
-    commands.insert_resource(DebugPlaySceneId::default());
-    // start game in map mode
-    state_manager.push(&mut commands, GameState::Map);
-
-    // load assets
-    if let Err(e) = item_manager.load_folder(Path::new(ASSETS_PATH).join("items")) {
-        warn!("could not load items: {e}")
-    };
-    if let Err(e) = map_manager.load_folder(Path::new(ASSETS_PATH).join("maps")) {
-        warn!("could not load maps: {e}")
-    };
-    if let Err(e) = scene_manager.load_folder(Path::new(ASSETS_PATH).join("scenes")) {
-        warn!("could not load scene: {e}")
+/// The value following `flag`, if it was passed. `usage` describes the value
+/// for when it's missing.
+fn flag_value<'a>(args: &'a [String], flag: &str, usage: &str) -> anyhow::Result<Option<&'a str>> {
+    let Some(i) = args.iter().position(|arg| arg == flag) else {
+        return Ok(None);
     };
-    // server.load_folder(Path::new(ASSETS_PATH).join("images"));
-
-    // spawn player
-    utils::spawn_player(&mut commands, &item_manager, "Jake", &["dragonbone-sword"]);
+    match value_after(args, i) {
+        Some(value) => Ok(Some(value)),
+        None => anyhow::bail!("usage: {flag} {usage}"),
+    }
+}
 
-    utils::spawn_npc(
-        &mut commands,
-        npc_query,
-        NpcId(String::from("narrator")),
-        Character {
-            name: String::from(""),
-            ..default()
-        },
-    );
+/// The values following every use of `flag`, in order.
+fn flag_values<'a>(args: &'a [String], flag: &str, usage: &str) -> anyhow::Result<Vec<&'a str>> {
+    args.iter()
+        .enumerate()
+        .filter(|(_, arg)| *arg == flag)
+        .map(|(i, _)| match value_after(args, i) {
+            Some(value) => Ok(value),
+            None => anyhow::bail!("usage: {flag} {usage}"),
+        })
+        .collect()
 }
 
-#[derive(Resource, Default)]
-pub struct DebugPlaySceneId(String);
+/// The argument after `args[i]`, unless it is another flag.
+fn value_after(args: &[String], i: usize) -> Option<&str> {
+    args.get(i + 1)
+        .map(String::as_str)
+        .filter(|value| !value.starts_with("--"))
+}
 
-fn debug_quit_immediately(mut exit_event: EventWriter<AppExit>) {
-    exit_event.write(AppExit::Success);
+fn usage() -> String {
+    let mut usage = String::from("usage: gizmo [options]\n\noptions:\n");
+    for (flag, value, help) in FLAGS {
+        usage += &format!("  {:<34}{help}\n", format!("{flag} {value}"));
+    }
+    usage
 }
@@ -1,16 +1,31 @@
 use std::path::{Path, PathBuf};
 
 use bevy::{
-    log::{error, info},
+    asset::{Asset, AssetEvent, AssetId, AssetServer, Assets},
+    log::{error, info, warn},
     platform::collections::HashMap,
-    prelude::Resource,
+    prelude::{EventReader, Res, ResMut, Resource},
+    reflect::TypePath,
 };
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
-use crate::{Definitions, StaticCommands, TODO, types::Position};
+use crate::{
+    AssetPacks, Definitions, LoadDiagnostic, StaticCommands, TODO, read_data, types::Position,
+    utils,
+};
 
 #[derive(
-    Deserialize, Debug, Hash, Clone, PartialEq, Eq, derive_more::From, derive_more::Display,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    Debug,
+    Hash,
+    Clone,
+    PartialEq,
+    Eq,
+    derive_more::From,
+    derive_more::Display,
 )]
 pub struct MapId(pub String);
 
@@ -20,14 +35,14 @@ impl MapId {
     }
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 pub struct MapLayers {
     ground: PathBuf,
     base: PathBuf,
     sky: Option<PathBuf>,
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub enum ActionPosition {
     #[serde(alias = "pos")]
@@ -36,7 +51,7 @@ pub enum ActionPosition {
     Range { start: Position, end: Position },
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub struct MapAction {
     #[serde(flatten)]
@@ -48,22 +63,49 @@ pub struct MapAction {
     commands: StaticCommands,
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Asset, TypePath, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct Map {
     id: MapId,
-    music: TODO,
+    music: Option<String>,
     layers: MapLayers,
     #[serde(flatten)]
-    definitions: Definitions,
+    pub(crate) definitions: Definitions,
     #[serde(alias = "player-pos")]
     player_position: Position,
     actions: Vec<MapAction>,
 }
 
+impl Map {
+    pub fn id(&self) -> &MapId {
+        &self.id
+    }
+
+    pub fn music(&self) -> Option<&str> {
+        self.music.as_deref()
+    }
+
+    /// Where the player starts on this map.
+    pub fn player_position(&self) -> Position {
+        self.player_position
+    }
+}
+
+/// The map the player is currently exploring.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct CurrentMap(pub MapId);
+
+/// Where the player is on the current map.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerPosition(pub Position);
+
 #[derive(Resource, Debug, Clone, Default)]
 pub struct MapManager {
     pub(crate) maps: HashMap<MapId, Map>,
+    /// Which map each loaded map asset defined, so it can be unloaded.
+    assets: HashMap<AssetId<Map>, MapId>,
+    /// The asset pack each map was defined by.
+    origins: HashMap<MapId, usize>,
 }
 
 impl MapManager {
@@ -73,33 +115,75 @@ impl MapManager {
 
     pub fn load_map<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<()> {
         info!("loading map: {:?}", path.as_ref());
-        let map_json = std::fs::read_to_string(path)?;
-        let map: Map = serde_json::from_str(&map_json)?;
+        let map: Map = read_data(path)?;
+        if self.maps.contains_key(&map.id) {
+            warn!("map {:?} is defined more than once, replacing it", map.id);
+        }
         self.maps.insert(map.id.clone(), map);
         Ok(())
     }
 
+    /// Keeps the manager in sync with the maps loaded by the asset server.
+    /// Maps from later asset packs override earlier ones.
+    pub fn handle_asset_events(
+        mut map_manager: ResMut<Self>,
+        mut asset_events: EventReader<AssetEvent<Map>>,
+        maps: Res<Assets<Map>>,
+        asset_server: Res<AssetServer>,
+        packs: Res<AssetPacks>,
+    ) {
+        for event in asset_events.read() {
+            match event {
+                AssetEvent::Added { id } | AssetEvent::Modified { id } => {
+                    let Some(map) = maps.get(*id) else {
+                        continue;
+                    };
+                    let priority = asset_server
+                        .get_path(*id)
+                        .map(|path| packs.priority(path.source()))
+                        .unwrap_or_default();
+                    // a reloaded file doesn't conflict with its old version
+                    let reloaded = map_manager.assets.get(id) == Some(&map.id);
+                    let current = map_manager
+                        .origins
+                        .get(&map.id)
+                        .copied()
+                        .filter(|_| !reloaded);
+                    if !packs.resolve("map", &map.id, priority, current) {
+                        continue;
+                    }
+                    info!("loaded map: {:?}", map.id);
+                    map_manager.assets.retain(|_, map_id| *map_id != map.id);
+                    map_manager.assets.insert(*id, map.id.clone());
+                    map_manager.origins.insert(map.id.clone(), priority);
+                    map_manager.maps.insert(map.id.clone(), map.clone());
+                }
+                AssetEvent::Removed { id } => {
+                    if let Some(map_id) = map_manager.assets.remove(id) {
+                        map_manager.origins.remove(&map_id);
+                        map_manager.maps.remove(&map_id);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn get_map(&self, id: &MapId) -> Option<&Map> {
+        self.maps.get(id)
+    }
+
     pub fn with_load_map<P: AsRef<Path>>(mut self, path: P) -> anyhow::Result<Self> {
         self.load_map(path)?;
         Ok(self)
     }
 
     pub fn load_folder<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<()> {
-        path.as_ref()
-            .read_dir()?
-            .filter_map(Result::ok)
-            .filter(|entry| {
-                entry
-                    .path()
-                    .extension()
-                    .map(|ext| ext == "json")
-                    .unwrap_or(false)
-            })
-            .for_each(|entry| {
-                if let Err(e) = self.load_map(entry.path()) {
-                    error!("failed to load map: {e}");
-                }
-            });
+        utils::data_files(path)?.into_iter().for_each(|path| {
+            if let Err(e) = self.load_map(&path) {
+                error!("failed to load map: {}", LoadDiagnostic::new(&path, &e));
+            }
+        });
         Ok(())
     }
 
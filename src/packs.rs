@@ -0,0 +1,330 @@
+use std::{
+    collections::BTreeSet,
+    fmt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{
+    asset::{
+        AssetPath,
+        io::{AssetSource, AssetSourceId},
+    },
+    prelude::*,
+};
+use serde::Deserialize;
+
+use crate::ASSETS_PATH;
+
+/// The manifest at the root of every asset pack.
+pub const MANIFEST_FILE: &str = "pack.json";
+/// How long to wait for a changed file to settle before reloading it.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PackManifest {
+    pub name: String,
+    pub version: String,
+    /// Names of packs that have to be loaded before this one.
+    #[serde(default, alias = "deps")]
+    pub dependencies: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetPack {
+    pub manifest: PackManifest,
+    pub path: PathBuf,
+}
+
+impl AssetPack {
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let manifest_path = path.as_ref().join(MANIFEST_FILE);
+        info!("loading asset pack: {manifest_path:?}");
+        let manifest_json = std::fs::read_to_string(&manifest_path)
+            .map_err(|e| anyhow::anyhow!("{}: {e}", manifest_path.display()))?;
+        let manifest = serde_json::from_str(&manifest_json)
+            .map_err(|e| anyhow::anyhow!("{}: {e}", manifest_path.display()))?;
+        Ok(Self {
+            manifest,
+            path: path.as_ref().to_path_buf(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.manifest.name
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PackProblem {
+    DuplicateName(String),
+    MissingDependency {
+        pack: String,
+        dependency: String,
+    },
+    /// The dependency exists, but is loaded after the pack that needs it.
+    DependencyOrder {
+        pack: String,
+        dependency: String,
+    },
+}
+
+impl fmt::Display for PackProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackProblem::DuplicateName(name) => write!(f, "more than one pack is named `{name}`"),
+            PackProblem::MissingDependency { pack, dependency } => {
+                write!(
+                    f,
+                    "pack `{pack}` depends on `{dependency}`, which is missing"
+                )
+            }
+            PackProblem::DependencyOrder { pack, dependency } => {
+                write!(
+                    f,
+                    "pack `{pack}` depends on `{dependency}`, which is loaded after it"
+                )
+            }
+        }
+    }
+}
+
+/// Asset packs in load order. The first is the base game, read from the
+/// default asset source, and each later pack overrides or extends the items,
+/// maps and scenes of the packs before it.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct AssetPacks {
+    packs: Vec<AssetPack>,
+}
+
+/// Only the base game, without reading its manifest.
+impl Default for AssetPacks {
+    fn default() -> Self {
+        let base = AssetPack {
+            manifest: PackManifest {
+                name: String::from("base"),
+                version: String::from(env!("CARGO_PKG_VERSION")),
+                dependencies: Vec::new(),
+            },
+            path: PathBuf::from(ASSETS_PATH),
+        };
+        Self { packs: vec![base] }
+    }
+}
+
+impl AssetPacks {
+    /// Loads the manifests of the base pack at `base_path` and every pack in
+    /// `pack_paths`, in order.
+    pub fn load<P: AsRef<Path>>(base_path: P, pack_paths: &[PathBuf]) -> anyhow::Result<Self> {
+        let packs = std::iter::once(base_path.as_ref())
+            .chain(pack_paths.iter().map(PathBuf::as_path))
+            .map(AssetPack::load)
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { packs })
+    }
+
+    /// The base game's asset folder.
+    pub fn base_path(&self) -> &Path {
+        &self.packs[0].path
+    }
+
+    /// The asset path of `path` in the last pack that has it, so later packs
+    /// can replace sounds, music and images too.
+    pub fn find(&self, path: &Path) -> Option<AssetPath<'static>> {
+        (0..self.packs.len())
+            .rev()
+            .find(|&i| self.packs[i].path.join(path).exists())
+            .map(|i| AssetPath::from(path.to_path_buf()).with_source(self.source(i)))
+    }
+
+    /// The names of the files in `folder` of every pack, without duplicates.
+    pub fn files(&self, folder: &str) -> BTreeSet<PathBuf> {
+        self.packs
+            .iter()
+            .filter_map(|pack| pack.path.join(folder).read_dir().ok())
+            .flatten()
+            .filter_map(Result::ok)
+            .map(|entry| Path::new(folder).join(entry.file_name()))
+            .collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &AssetPack> {
+        self.packs.iter()
+    }
+
+    pub fn check(&self) -> Vec<PackProblem> {
+        let mut problems = Vec::new();
+        for (i, pack) in self.packs.iter().enumerate() {
+            if self.packs[..i].iter().any(|p| p.name() == pack.name()) {
+                problems.push(PackProblem::DuplicateName(pack.name().to_string()));
+            }
+            for dependency in &pack.manifest.dependencies {
+                let position = self.packs.iter().position(|p| p.name() == dependency);
+                let problem = match position {
+                    None => PackProblem::MissingDependency {
+                        pack: pack.name().to_string(),
+                        dependency: dependency.clone(),
+                    },
+                    Some(position) if position > i => PackProblem::DependencyOrder {
+                        pack: pack.name().to_string(),
+                        dependency: dependency.clone(),
+                    },
+                    Some(_) => continue,
+                };
+                problems.push(problem);
+            }
+        }
+        problems
+    }
+
+    /// The asset source files in `pack` are loaded from.
+    pub fn source(&self, pack: usize) -> AssetSourceId<'static> {
+        match pack {
+            0 => AssetSourceId::Default,
+            _ => AssetSourceId::Name(self.packs[pack].manifest.name.clone().into()),
+        }
+    }
+
+    /// The position of the pack that `source` belongs to. Later packs win
+    /// conflicts.
+    pub fn priority(&self, source: &AssetSourceId) -> usize {
+        match source {
+            AssetSourceId::Default => 0,
+            AssetSourceId::Name(name) => self
+                .packs
+                .iter()
+                .position(|pack| pack.name() == &**name)
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn name(&self, pack: usize) -> &str {
+        self.packs
+            .get(pack)
+            .map(AssetPack::name)
+            .unwrap_or("unknown")
+    }
+
+    /// Registers an asset source for every pack after the base one. Has to be
+    /// called before the `AssetPlugin` is added.
+    pub fn register_sources(&self, app: &mut App) {
+        for (i, pack) in self.packs.iter().enumerate().skip(1) {
+            let path = pack.path.to_string_lossy().to_string();
+            app.register_asset_source(
+                self.source(i),
+                AssetSource::build()
+                    .with_reader(AssetSource::get_default_reader(path.clone()))
+                    .with_watcher(AssetSource::get_default_watcher(path, WATCH_DEBOUNCE)),
+            );
+        }
+    }
+
+    /// Whether a definition of `id` from the pack at `priority` should replace
+    /// the current one, which is from the pack at `current`. Logs which pack
+    /// won if they differ.
+    pub fn resolve(
+        &self,
+        kind: &str,
+        id: &dyn fmt::Display,
+        priority: usize,
+        current: Option<usize>,
+    ) -> bool {
+        match current {
+            Some(current) if current > priority => {
+                info!(
+                    "{kind} `{id}`: pack `{}` overrides `{}`",
+                    self.name(current),
+                    self.name(priority)
+                );
+                false
+            }
+            Some(current) if current < priority => {
+                info!(
+                    "{kind} `{id}`: pack `{}` overrides `{}`",
+                    self.name(priority),
+                    self.name(current)
+                );
+                true
+            }
+            Some(_) => {
+                warn!(
+                    "{kind} `{id}` is defined more than once in pack `{}`",
+                    self.name(priority)
+                );
+                true
+            }
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use bevy::asset::io::AssetSourceId;
+
+    use crate::{AssetPack, AssetPacks, PackManifest, PackProblem};
+
+    fn pack(name: &str, dependencies: &[&str]) -> AssetPack {
+        AssetPack {
+            manifest: PackManifest {
+                name: name.into(),
+                version: "1.0.0".into(),
+                dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            },
+            path: name.into(),
+        }
+    }
+
+    #[test]
+    fn detects_missing_and_misordered_dependencies() {
+        let packs = AssetPacks {
+            packs: vec![
+                pack("base", &[]),
+                pack("mod", &["dlc", "base"]),
+                pack("dlc", &["base", "expansion"]),
+            ],
+        };
+        assert_eq!(
+            packs.check(),
+            [
+                PackProblem::DependencyOrder {
+                    pack: "mod".into(),
+                    dependency: "dlc".into()
+                },
+                PackProblem::MissingDependency {
+                    pack: "dlc".into(),
+                    dependency: "expansion".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn later_packs_replace_files() {
+        let assets = concat!(env!("CARGO_MANIFEST_DIR"), "/assets");
+        let mut base = pack("base", &[]);
+        base.path = assets.into();
+        let mut dlc = pack("dlc", &["base"]);
+        dlc.path = assets.into();
+        let packs = AssetPacks {
+            packs: vec![base, dlc],
+        };
+
+        let portrait = packs.find(Path::new("images/jake.png")).unwrap();
+        assert_eq!(portrait.source(), &AssetSourceId::Name("dlc".into()));
+        assert!(packs.find(Path::new("images/nobody.png")).is_none());
+
+        let images = Path::new(assets).join("images").read_dir().unwrap();
+        assert_eq!(packs.files("images").len(), images.count());
+    }
+
+    #[test]
+    fn base_manifest_loads() {
+        let packs = AssetPacks::load(concat!(env!("CARGO_MANIFEST_DIR"), "/assets"), &[]).unwrap();
+        assert_eq!(packs.name(0), "base");
+        assert!(packs.check().is_empty());
+    }
+}
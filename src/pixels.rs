@@ -31,7 +31,7 @@ impl Rgba8 {
             red,
             green,
             blue,
-            alpha: std::u8::MAX,
+            alpha: u8::MAX,
         }
     }
 
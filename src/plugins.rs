@@ -0,0 +1,394 @@
+use bevy::{audio::AddAudioSource, prelude::*};
+use bevy_egui::EguiContextPass;
+
+use crate::{
+    AssetPacks, AttackEvent, Blip, COVERAGE_PATH, DEBUG, DamageEvent, DataLoadedEvent, DeathEvent,
+    DebugPlaySceneId, DialogueCoverage, DialogueHistory, EndBattleEvent, EndSceneEvent,
+    EnterMapEvent, ExperienceCurve, Frontend, GameManifest, GameState, ItemFile, ItemManager,
+    LevelUpCursor, LevelUpEvent, LoadDiagnostics, Map, MapManager, Music, PlaySceneEvent,
+    PlaySoundEvent, Portraits, RemoveItemEvent, RewardExperienceEvent, RewardItemEvent,
+    RewardLootEvent, SaveGameEvent, Scene, SceneManager, ScenePlayer, ShowBacklog, SpawnNpcEvent,
+    SpawnVendorEvent, SpeechBlipEvent, StartBattleEvent, StateManager, StaticCommandsEvent,
+    SyncData, Toasts, Typewriter, TypewriterSettings, UpdateNpcEvent, VoiceManager, backlog_ui,
+    battle_ui, check_data_loaded, debug_ui, dialogue_ui, dialogue_ui_input, draw_random_pixels,
+    exit_on_esc, fade_music, level_up_ui, load_data_folders, load_npc_portraits, map_ui,
+    quick_save, record_dialogue_coverage, record_dialogue_history, register_data_asset,
+    setup_pixel_buffer, setup_portraits, spawn_characters, start_game, terminal_battle_input,
+    terminal_battle_ui, terminal_dialogue_input, terminal_dialogue_ui, terminal_exit,
+    terminal_map_input, terminal_map_ui, terminal_quick_save, toast_ui, update_music,
+    update_toasts, update_typewriter,
+};
+
+/// Which parts of gizmo a game uses, and how it starts.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct GizmoSettings {
+    /// Scenes, dialogue and the commands they run.
+    pub dialogue: bool,
+    /// Turn-based battles, experience and levelling up.
+    pub battle: bool,
+    /// Maps to explore.
+    pub map: bool,
+    /// Items, loot and inventories.
+    pub items: bool,
+    /// The UI the game is drawn with, or `None` for a game that brings its
+    /// own.
+    pub frontend: Option<Frontend>,
+    /// The player, global characters and where the game starts.
+    pub game: GameManifest,
+}
+
+impl Default for GizmoSettings {
+    fn default() -> Self {
+        Self {
+            dialogue: true,
+            battle: true,
+            map: true,
+            items: true,
+            frontend: Some(Frontend::Window),
+            game: GameManifest::default(),
+        }
+    }
+}
+
+/// Everything a gizmo game needs: game state, data loaded from the asset
+/// packs, audio, and the plugins picked by [`GizmoSettings`].
+///
+/// Expects Bevy's plugins to be added first, including `StatesPlugin` and the
+/// asset plugin, and the frontend's plugins: `EguiPlugin` for
+/// [`Frontend::Window`], `RatatuiPlugins` for [`Frontend::Terminal`]. Add
+/// [`AssetPacks`] as a resource to load more than the base game.
+#[derive(Default)]
+pub struct GizmoPlugin {
+    pub settings: GizmoSettings,
+}
+
+impl Plugin for GizmoPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = &self.settings;
+        let state_manager = StateManager::new(GameState::Map);
+        app.insert_state(state_manager.get().expect("state exists."))
+            .insert_resource(state_manager)
+            .insert_resource(settings.clone())
+            .insert_resource(settings.game.clone())
+            .insert_resource(settings.game.experience.clone())
+            .init_resource::<AssetPacks>()
+            .insert_resource(VoiceManager::new())
+            .init_resource::<Toasts>()
+            .init_resource::<LoadDiagnostics>()
+            .add_event::<DataLoadedEvent>()
+            .add_event::<SaveGameEvent>()
+            .add_systems(Startup, (setup, load_data_folders))
+            .add_systems(Last, check_data_loaded.after(SyncData))
+            .add_systems(Update, update_toasts)
+            .add_systems(
+                Update,
+                (spawn_characters, start_game)
+                    .chain()
+                    .run_if(on_event::<DataLoadedEvent>),
+            )
+            .add_systems(PostUpdate, SaveGameEvent::handler)
+            .add_systems(Last, save_dialogue_coverage.run_if(on_event::<AppExit>));
+
+        if settings.items {
+            app.add_plugins(ItemsPlugin);
+        }
+        if settings.battle {
+            app.add_plugins(BattlePlugin);
+        }
+        if settings.map {
+            app.add_plugins(MapPlugin);
+        }
+        if settings.dialogue {
+            app.add_plugins(DialoguePlugin);
+        }
+        match settings.frontend {
+            Some(Frontend::Window) => app.add_plugins(EguiUiPlugin),
+            Some(Frontend::Terminal) => app.add_plugins(TerminalUiPlugin),
+            None => app,
+        };
+        register_audio(app);
+    }
+}
+
+/// Items, loot and inventories. Item files are loaded when the asset plugin is
+/// added; otherwise insert an [`ItemManager`] of your own.
+pub struct ItemsPlugin;
+
+impl Plugin for ItemsPlugin {
+    fn build(&self, app: &mut App) {
+        add_events(app);
+        app.init_resource::<ItemManager>().add_systems(
+            PostUpdate,
+            (
+                RewardItemEvent::handler,
+                RewardLootEvent::handler,
+                RemoveItemEvent::handler,
+            ),
+        );
+        if app.is_plugin_added::<AssetPlugin>() {
+            register_data_asset::<ItemFile, _>(
+                app,
+                "items",
+                &["items.json", "items.yaml", "items.yml", "items.ron"],
+                ItemManager::handle_asset_events,
+            );
+        }
+    }
+}
+
+/// Battles, experience and levelling up. Needs the [`StateManager`] from
+/// [`GizmoPlugin`].
+pub struct BattlePlugin;
+
+impl Plugin for BattlePlugin {
+    fn build(&self, app: &mut App) {
+        add_events(app);
+        // weapons decide how much damage attacks do
+        app.init_resource::<ItemManager>()
+            .init_resource::<ExperienceCurve>()
+            .add_systems(
+                PostUpdate,
+                (
+                    AttackEvent::handler,
+                    DamageEvent::handler,
+                    DeathEvent::handler,
+                    RewardExperienceEvent::handler,
+                    LevelUpEvent::handler,
+                    StartBattleEvent::handler,
+                    EndBattleEvent::handler,
+                ),
+            );
+    }
+}
+
+/// Maps to explore. Map files are loaded when the asset plugin is added.
+pub struct MapPlugin;
+
+impl Plugin for MapPlugin {
+    fn build(&self, app: &mut App) {
+        add_events(app);
+        app.init_resource::<MapManager>()
+            .add_systems(PostUpdate, EnterMapEvent::handler);
+        if app.is_plugin_added::<AssetPlugin>() {
+            register_data_asset::<Map, _>(
+                app,
+                "maps",
+                &["map.json", "map.yaml", "map.yml", "map.ron"],
+                MapManager::handle_asset_events,
+            );
+        }
+    }
+}
+
+/// Scenes and the dialogue in them. Scene files are loaded when the asset
+/// plugin is added. Needs the [`StateManager`] from [`GizmoPlugin`].
+pub struct DialoguePlugin;
+
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        add_events(app);
+        // scenes stock their vendors with items
+        app.init_resource::<ItemManager>()
+            .init_resource::<SceneManager>()
+            .init_resource::<DialogueHistory>()
+            .init_resource::<DialogueCoverage>()
+            .add_systems(
+                PostUpdate,
+                (
+                    PlaySceneEvent::handler,
+                    StaticCommandsEvent::handler,
+                    EndSceneEvent::handler,
+                    UpdateNpcEvent::handler,
+                    SpawnVendorEvent::handler,
+                ),
+            )
+            // characters from a scene should exist before its first line is shown
+            .add_systems(
+                PostUpdate,
+                SpawnNpcEvent::handler.after(PlaySceneEvent::handler),
+            )
+            .add_systems(
+                Update,
+                (
+                    ScenePlayer::update,
+                    record_dialogue_history,
+                    record_dialogue_coverage,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Dialogue)),
+            );
+        if app.is_plugin_added::<AssetPlugin>() {
+            register_data_asset::<Scene, _>(
+                app,
+                "scenes",
+                &["scene.json", "scene.yaml", "scene.yml", "scene.ron"],
+                SceneManager::handle_asset_events,
+            );
+        }
+    }
+}
+
+/// Draws the game in a window with egui. Needs `EguiPlugin`.
+pub struct EguiUiPlugin;
+
+impl Plugin for EguiUiPlugin {
+    fn build(&self, app: &mut App) {
+        add_ui_resources(app);
+        app.add_systems(Startup, (spawn_camera, setup_pixel_buffer, setup_portraits))
+            .add_systems(Update, (exit_on_esc, quick_save))
+            .add_systems(Update, draw_random_pixels.run_if(in_state(GameState::Map)))
+            .add_systems(
+                PostUpdate,
+                load_npc_portraits.run_if(resource_exists::<Portraits>),
+            )
+            .add_systems(EguiContextPass, debug_ui.run_if(|| DEBUG))
+            .add_systems(EguiContextPass, toast_ui)
+            .add_systems(
+                EguiContextPass,
+                (map_ui, level_up_ui).run_if(in_state(GameState::Map)),
+            )
+            .add_systems(
+                EguiContextPass,
+                (dialogue_ui, dialogue_ui_input, backlog_ui).run_if(in_state(GameState::Dialogue)),
+            )
+            .add_systems(
+                EguiContextPass,
+                battle_ui.run_if(in_state(GameState::Battle)),
+            );
+    }
+}
+
+/// Draws the game in the terminal with ratatui. Needs `RatatuiPlugins`.
+pub struct TerminalUiPlugin;
+
+impl Plugin for TerminalUiPlugin {
+    fn build(&self, app: &mut App) {
+        add_ui_resources(app);
+        app.init_resource::<LevelUpCursor>()
+            .add_systems(PreUpdate, (terminal_exit, terminal_quick_save))
+            .add_systems(
+                Update,
+                (terminal_map_input, terminal_map_ui)
+                    .chain()
+                    .run_if(in_state(GameState::Map)),
+            )
+            .add_systems(
+                Update,
+                (terminal_dialogue_input, terminal_dialogue_ui)
+                    .chain()
+                    .run_if(in_state(GameState::Dialogue)),
+            )
+            .add_systems(
+                Update,
+                (terminal_battle_input, terminal_battle_ui)
+                    .chain()
+                    .run_if(in_state(GameState::Battle)),
+            );
+    }
+}
+
+/// Adds every gizmo event. Each plugin adds them all, since scenes and map
+/// actions can send events handled by plugins the game left out.
+fn add_events(app: &mut App) {
+    app.add_event::<AttackEvent>()
+        .add_event::<DamageEvent>()
+        .add_event::<DeathEvent>()
+        .add_event::<PlaySceneEvent>()
+        .add_event::<EndSceneEvent>()
+        .add_event::<StaticCommandsEvent>()
+        .add_event::<RewardItemEvent>()
+        .add_event::<RewardLootEvent>()
+        .add_event::<RemoveItemEvent>()
+        .add_event::<RewardExperienceEvent>()
+        .add_event::<LevelUpEvent>()
+        .add_event::<PlaySoundEvent>()
+        .add_event::<SpeechBlipEvent>()
+        .add_event::<StartBattleEvent>()
+        .add_event::<EndBattleEvent>()
+        .add_event::<SpawnNpcEvent>()
+        .add_event::<SpawnVendorEvent>()
+        .add_event::<EnterMapEvent>()
+        .add_event::<UpdateNpcEvent>();
+}
+
+/// What both frontends share. The UI shows every part of the game, so parts
+/// the game left out are shown empty.
+fn add_ui_resources(app: &mut App) {
+    app.init_resource::<ItemManager>()
+        .init_resource::<MapManager>()
+        .init_resource::<SceneManager>()
+        .init_resource::<DialogueHistory>()
+        .init_resource::<Typewriter>()
+        .init_resource::<TypewriterSettings>()
+        .init_resource::<ShowBacklog>()
+        // value for text input for selecting scenes
+        .init_resource::<DebugPlaySceneId>()
+        .add_systems(
+            Update,
+            update_typewriter.run_if(in_state(GameState::Dialogue)),
+        );
+}
+
+fn register_audio(app: &mut App) {
+    if !app.is_plugin_added::<bevy::audio::AudioPlugin>() {
+        info!("audio is disabled, music and sounds will not play");
+        return;
+    }
+    add_events(app);
+    // music follows the current map and scene
+    app.init_resource::<MapManager>()
+        .init_resource::<SceneManager>()
+        .add_audio_source::<Blip>()
+        .init_resource::<Music>()
+        .add_systems(Update, (update_music, fade_music).chain())
+        .add_systems(
+            PostUpdate,
+            (PlaySoundEvent::handler, SpeechBlipEvent::handler),
+        );
+}
+
+fn setup(
+    mut commands: Commands,
+    mut voice_manager: ResMut<VoiceManager>,
+    mut state_manager: ResMut<StateManager>,
+    packs: Res<AssetPacks>,
+) {
+    // start game in map mode
+    state_manager.push(&mut commands, GameState::Map);
+
+    // items, maps and scenes are loaded by the asset server, see `register_data_asset`
+
+    // later packs replace voices with the same ID
+    for (i, pack) in packs.iter().enumerate() {
+        let path = pack.path.join("voices");
+        // only the base game has to have voices
+        if i > 0 && !path.is_dir() {
+            continue;
+        }
+        if let Err(e) = voice_manager.load_folder(path) {
+            warn!("could not load voices: {e}")
+        };
+    }
+}
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn(Camera2d);
+}
+
+/// Adds what this session reached to the coverage saved by earlier ones.
+fn save_dialogue_coverage(
+    coverage: Option<Res<DialogueCoverage>>,
+    scene_player: Option<Res<ScenePlayer>>,
+    packs: Res<AssetPacks>,
+) {
+    let Some(coverage) = coverage else {
+        return;
+    };
+    let mut coverage = coverage.clone();
+    if let Some(scene_player) = scene_player {
+        coverage.record(&scene_player);
+    }
+    if let Err(e) = coverage.merge_into_file(packs.base_path().join(COVERAGE_PATH)) {
+        warn!("could not save dialogue coverage: {e}")
+    }
+}
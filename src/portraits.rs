@@ -0,0 +1,94 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{asset::AssetPath, platform::collections::HashMap, prelude::*};
+use bevy_egui::{EguiUserTextures, egui};
+
+use crate::{AssetPacks, Npc, NpcImage};
+
+struct Portrait {
+    handle: Handle<Image>,
+    texture_id: egui::TextureId,
+}
+
+/// NPC portraits registered as egui user textures, keyed by asset path.
+#[derive(Resource, Default)]
+pub struct Portraits {
+    portraits: HashMap<PathBuf, Portrait>,
+}
+
+impl Portraits {
+    pub fn load(
+        &mut self,
+        path: &Path,
+        asset_server: &AssetServer,
+        packs: &AssetPacks,
+        user_textures: &mut EguiUserTextures,
+    ) -> egui::TextureId {
+        if let Some(portrait) = self.portraits.get(path) {
+            return portrait.texture_id;
+        }
+        info!("loading portrait: {path:?}");
+        // missing portraits load from the base pack and fail, showing the fallback
+        let asset_path = packs
+            .find(path)
+            .unwrap_or_else(|| AssetPath::from(path.to_path_buf()));
+        let handle: Handle<Image> = asset_server.load(asset_path);
+        let texture_id = user_textures.add_image(handle.clone());
+        self.portraits
+            .insert(path.to_path_buf(), Portrait { handle, texture_id });
+        texture_id
+    }
+
+    /// Returns the texture for `path`, or the fallback portrait if it failed
+    /// to load.
+    pub fn get(&self, path: &Path, asset_server: &AssetServer) -> Option<egui::TextureId> {
+        match self.portraits.get(path) {
+            Some(portrait) if !asset_server.load_state(&portrait.handle).is_failed() => {
+                Some(portrait.texture_id)
+            }
+            _ => self
+                .portraits
+                .get(&NpcImage::default().0)
+                .map(|fallback| fallback.texture_id),
+        }
+    }
+}
+
+/// Preloads every image in the `images` folder of each pack so expression
+/// swaps show up without waiting on the asset server.
+pub fn setup_portraits(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut user_textures: ResMut<EguiUserTextures>,
+    packs: Res<AssetPacks>,
+) {
+    let mut portraits = Portraits::default();
+    portraits.load(
+        &NpcImage::default().0,
+        &asset_server,
+        &packs,
+        &mut user_textures,
+    );
+
+    packs
+        .files("images")
+        .into_iter()
+        .filter(|path| path.extension().map(|ext| ext == "png").unwrap_or(false))
+        .for_each(|path| {
+            portraits.load(&path, &asset_server, &packs, &mut user_textures);
+        });
+
+    commands.insert_resource(portraits);
+}
+
+pub fn load_npc_portraits(
+    npc_query: Query<&Npc, Changed<Npc>>,
+    mut portraits: ResMut<Portraits>,
+    asset_server: Res<AssetServer>,
+    packs: Res<AssetPacks>,
+    mut user_textures: ResMut<EguiUserTextures>,
+) {
+    for npc in npc_query.iter() {
+        portraits.load(&npc.image.0, &asset_server, &packs, &mut user_textures);
+    }
+}
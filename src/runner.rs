@@ -0,0 +1,326 @@
+use std::path::Path;
+
+use bevy::{
+    ecs::system::RunSystemOnce,
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+    state::app::StatesPlugin,
+};
+use bevy_rand::prelude::*;
+
+use crate::{
+    BattlePlugin, DialogueCoverage, DialogueHistory, DialoguePlugin, EndBattleEvent, EndSceneEvent,
+    ExperienceCurve, FormattedLine, GameManifest, GameState, HistoryEntry, ItemManager,
+    ItemsPlugin, MapManager, MapPlugin, NpcId, PlaySceneEvent, Player, PlayerSetup, RpgEntity,
+    SceneBookmark, SceneId, SceneManager, ScenePlayer, ScenePlayerInput, SceneSectionId,
+    StartBattleEvent, StateManager, StaticCommands, StaticCommandsEvent, spawn_characters,
+};
+
+/// Frames to run after each input so events and state changes settle.
+const SETTLE_FRAMES: usize = 4;
+
+/// Commands that ran, and battles that were started, in the order they happened.
+#[derive(Resource, Debug, Default)]
+struct RunnerLog {
+    executed: HashSet<SceneBookmark>,
+    commands: Vec<(SceneBookmark, StaticCommands)>,
+    battles: Vec<NpcId>,
+}
+
+fn record_commands(
+    mut log: ResMut<RunnerLog>,
+    mut static_commands_events: EventReader<StaticCommandsEvent>,
+    mut start_battle_events: EventReader<StartBattleEvent>,
+) {
+    for StaticCommandsEvent(bookmark, commands) in static_commands_events.read() {
+        // flattened commands are there even when a line has none
+        if *commands == StaticCommands::default() {
+            continue;
+        }
+        // section commands are resent every frame until they run
+        if log.executed.insert(bookmark.to_owned()) {
+            log.commands
+                .push((bookmark.to_owned(), commands.to_owned()));
+        }
+    }
+    for StartBattleEvent(npc_id) in start_battle_events.read() {
+        log.battles.push(npc_id.to_owned());
+    }
+}
+
+/// Battles aren't simulated, so any battle a scene starts ends straight away.
+fn skip_battles(state: Res<State<GameState>>, mut end_battle_event: EventWriter<EndBattleEvent>) {
+    if *state.get() == GameState::Battle {
+        end_battle_event.write(EndBattleEvent);
+    }
+}
+
+fn apply_input(
+    In(input): In<ScenePlayerInput>,
+    scene_player: Option<ResMut<ScenePlayer>>,
+    mut scene_manager: ResMut<SceneManager>,
+    mut history: ResMut<DialogueHistory>,
+    mut end_scene_event: EventWriter<EndSceneEvent>,
+    mut scene_commands_event: EventWriter<StaticCommandsEvent>,
+    player_query: Query<&RpgEntity, With<Player>>,
+) -> bool {
+    let Some(mut scene_player) = scene_player else {
+        return false;
+    };
+    let scene = scene_player.scene().clone();
+    if let Some(response) = scene_player.input(
+        input,
+        &mut scene_manager,
+        &mut end_scene_event,
+        &mut scene_commands_event,
+    ) {
+        let player_name = player_query
+            .single()
+            .map(RpgEntity::name)
+            .unwrap_or_default();
+        let response = FormattedLine::new(&response, &scene_manager, player_name);
+        history.record_response(&scene, &response.text);
+    }
+    true
+}
+
+/// Everything a scene did during a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SceneRun {
+    pub transcript: Vec<HistoryEntry>,
+    pub variables: HashMap<String, String>,
+    pub entries: HashMap<SceneId, SceneSectionId>,
+    pub commands: Vec<(SceneBookmark, StaticCommands)>,
+    pub battles: Vec<NpcId>,
+    /// Whether the scene ended during the run.
+    pub finished: bool,
+}
+
+/// Plays scenes without a window or UI, driven by a list of inputs.
+pub struct SceneRunner {
+    app: App,
+}
+
+impl SceneRunner {
+    /// Creates a runner with the items and scenes under `assets_path`. Loot
+    /// rolls use `seed`, so runs are reproducible.
+    pub fn new(assets_path: &Path, seed: u64) -> anyhow::Result<Self> {
+        let item_manager = ItemManager::new().with_load_folder(assets_path.join("items"))?;
+        let scene_manager = SceneManager::new().with_load_folder(assets_path.join("scenes"))?;
+        Ok(Self::with_managers(item_manager, scene_manager, seed))
+    }
+
+    pub fn with_managers(
+        item_manager: ItemManager,
+        scene_manager: SceneManager,
+        seed: u64,
+    ) -> Self {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            StatesPlugin,
+            EntropyPlugin::<WyRand>::with_seed(seed.to_le_bytes()),
+        ))
+        .insert_state(GameState::Map)
+        .insert_resource(StateManager::new(GameState::Map))
+        .insert_resource(item_manager)
+        .insert_resource(MapManager::new())
+        .insert_resource(scene_manager)
+        .init_resource::<ExperienceCurve>()
+        .init_resource::<DialogueHistory>()
+        .init_resource::<DialogueCoverage>()
+        .init_resource::<RunnerLog>()
+        .insert_resource(GameManifest {
+            player: PlayerSetup {
+                equipped: Vec::new(),
+                ..default()
+            },
+            ..default()
+        })
+        .add_systems(Startup, spawn_characters)
+        .add_systems(PreUpdate, record_commands)
+        .add_systems(Update, skip_battles)
+        // without an asset plugin these only add gameplay, not data loading
+        .add_plugins((ItemsPlugin, BattlePlugin, MapPlugin, DialoguePlugin));
+        app.update();
+        Self { app }
+    }
+
+    fn settle(&mut self) {
+        for _ in 0..SETTLE_FRAMES {
+            self.app.update();
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.app.world().contains_resource::<ScenePlayer>()
+    }
+
+    pub fn play(&mut self, scene_id: &SceneId) -> anyhow::Result<()> {
+        if self.is_playing() {
+            anyhow::bail!("a scene is already playing");
+        }
+        self.app
+            .world_mut()
+            .send_event(PlaySceneEvent(scene_id.to_owned()));
+        self.settle();
+        if !self.is_playing() {
+            anyhow::bail!("could not play scene: {scene_id:?}");
+        }
+        Ok(())
+    }
+
+    pub fn input(&mut self, input: ScenePlayerInput) -> anyhow::Result<()> {
+        let applied = self
+            .app
+            .world_mut()
+            .run_system_once_with(apply_input, input)
+            .map_err(|e| anyhow::anyhow!("{e}"))?;
+        if !applied {
+            anyhow::bail!("no scene is playing, cannot apply input: {input:?}");
+        }
+        self.settle();
+        Ok(())
+    }
+
+    /// Indices of the responses the player can pick, if the scene is waiting
+    /// on one.
+    pub fn available_responses(&mut self) -> Option<Vec<usize>> {
+        let mut player_query = self
+            .app
+            .world_mut()
+            .query_filtered::<&RpgEntity, With<Player>>();
+        let world = self.app.world();
+        let player = player_query.single(world).ok()?;
+        let scene_player = world.get_resource::<ScenePlayer>()?;
+        let scene_manager = world.resource::<SceneManager>();
+        scene_player
+            .awaiting_response(scene_manager)
+            .then(|| scene_player.available_responses(scene_manager, &player.inventory))
+    }
+
+    /// Applies a comma separated script such as `select 1, continue, select 0`.
+    pub fn run_script(&mut self, script: &str) -> anyhow::Result<()> {
+        parse_script(script)?
+            .into_iter()
+            .try_for_each(|input| self.input(input))
+    }
+
+    /// Sections, lines and responses reached by every scene played so far.
+    pub fn coverage(&self) -> &DialogueCoverage {
+        self.app.world().resource::<DialogueCoverage>()
+    }
+
+    pub fn result(&self) -> SceneRun {
+        let world = self.app.world();
+        let scene_manager = world.resource::<SceneManager>();
+        let log = world.resource::<RunnerLog>();
+        SceneRun {
+            transcript: world.resource::<DialogueHistory>().entries().to_vec(),
+            variables: scene_manager.variables.clone(),
+            entries: scene_manager.entries.clone(),
+            commands: log.commands.clone(),
+            battles: log.battles.clone(),
+            finished: !self.is_playing(),
+        }
+    }
+}
+
+/// Parses inputs such as `select 1`, `continue`, `up`, `down` and `move 2`.
+pub fn parse_script(script: &str) -> anyhow::Result<Vec<ScenePlayerInput>> {
+    script
+        .split([',', '\n'])
+        .map(str::trim)
+        .filter(|step| !step.is_empty())
+        .map(|step| {
+            let mut words = step.split_whitespace();
+            let command = words.next().unwrap_or_default();
+            let index = words.next().map(str::parse::<usize>).transpose()?;
+            match (command, index) {
+                ("continue" | "c", None) => Ok(ScenePlayerInput::SelectCurrent),
+                ("select" | "s", Some(i)) => Ok(ScenePlayerInput::Select(i)),
+                ("select" | "s", None) => Ok(ScenePlayerInput::SelectCurrent),
+                ("move" | "m", Some(i)) => Ok(ScenePlayerInput::MoveTo(i)),
+                ("up", None) => Ok(ScenePlayerInput::MoveUp),
+                ("down", None) => Ok(ScenePlayerInput::MoveDown),
+                _ => anyhow::bail!("unknown script step: {step:?}"),
+            }
+        })
+        .collect()
+}
+
+/// Plays `scene_id` with the assets under `assets_path` and applies `script`.
+pub fn run_scene(assets_path: &Path, scene_id: &SceneId, script: &str) -> anyhow::Result<SceneRun> {
+    let mut runner = SceneRunner::new(assets_path, 0)?;
+    runner.play(scene_id)?;
+    runner.run_script(script)?;
+    Ok(runner.result())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::{HistoryEntry, SceneId, ScenePlayerInput, parse_script, run_scene};
+
+    const ASSETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets");
+
+    fn line(speaker: &str, text: &str) -> HistoryEntry {
+        HistoryEntry::Line {
+            scene: SceneId::new("test"),
+            speaker: speaker.into(),
+            text: text.into(),
+        }
+    }
+
+    fn response(text: &str) -> HistoryEntry {
+        HistoryEntry::Response {
+            scene: SceneId::new("test"),
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn parses_scripts() {
+        assert_eq!(
+            parse_script("select 1, continue,\nselect 0, up").unwrap(),
+            [
+                ScenePlayerInput::Select(1),
+                ScenePlayerInput::SelectCurrent,
+                ScenePlayerInput::Select(0),
+                ScenePlayerInput::MoveUp,
+            ]
+        );
+        assert!(parse_script("dance").is_err());
+    }
+
+    #[test]
+    fn variable_test_scene() {
+        let run = run_scene(
+            Path::new(ASSETS),
+            &SceneId::new("test"),
+            "select 0, continue, select 3, continue",
+        )
+        .unwrap();
+
+        assert_eq!(
+            run.transcript,
+            [
+                line("Mike", "variable test"),
+                response("set test-var: red"),
+                line("Mike", "variable set."),
+                line("Mike", "variable test"),
+                response("I am red!"),
+                line("Mike", "indeed, that is correct."),
+                line("Mike", "variable test"),
+            ]
+        );
+        assert_eq!(
+            run.variables.get("test-var").map(String::as_str),
+            Some("red")
+        );
+        assert_eq!(run.commands.len(), 1);
+        assert!(run.battles.is_empty());
+        assert!(!run.finished);
+    }
+}
@@ -1,19 +1,32 @@
 use std::{collections::HashSet, ops::Add, path::Path};
 
 use bevy::{
-    log::{error, info},
+    asset::{Asset, AssetEvent, AssetId, AssetServer, Assets},
+    log::{error, info, warn},
     platform::collections::HashMap,
-    prelude::{Commands, EventWriter, Resource},
+    prelude::{Commands, EventReader, EventWriter, Res, ResMut, Resource},
+    reflect::TypePath,
 };
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    Definitions, EndSceneEvent, NpcId, NpcImage, NpcVoice, SpawnNpcEvent, StartBattleEvent,
-    StaticCommands, StaticCommandsEvent, UpdateNpcEvent,
+    AssetPacks, Definitions, EndSceneEvent, Inventory, ItemId, LoadDiagnostic, NpcId, NpcImage,
+    NpcVoice, Skill, SpawnNpcEvent, SpawnVendorEvent, StaticCommands, StaticCommandsEvent,
+    StaticCommandsWriters, Stats, read_data, utils,
 };
 
 #[derive(
-    Deserialize, Debug, Hash, Clone, PartialEq, Eq, derive_more::From, derive_more::Display,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    Debug,
+    Hash,
+    Clone,
+    PartialEq,
+    Eq,
+    derive_more::From,
+    derive_more::Display,
 )]
 pub struct SceneSectionId(pub String);
 
@@ -24,7 +37,16 @@ impl Default for SceneSectionId {
 }
 
 #[derive(
-    Deserialize, Debug, Hash, Clone, PartialEq, Eq, derive_more::From, derive_more::Display,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    Debug,
+    Hash,
+    Clone,
+    PartialEq,
+    Eq,
+    derive_more::From,
+    derive_more::Display,
 )]
 pub struct SceneId(pub String);
 
@@ -34,7 +56,10 @@ impl SceneId {
     }
 }
 
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(
+    Serialize, Deserialize, Debug, Hash, Clone, PartialEq, Eq, PartialOrd, Ord, derive_more::Display,
+)]
+#[serde(transparent)]
 pub struct SceneBookmark(String);
 
 impl SceneBookmark {
@@ -59,25 +84,39 @@ impl SceneBookmark {
     }
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Asset, TypePath, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
 pub struct Scene {
     id: SceneId,
     music: Option<String>,
     #[serde(flatten)]
-    definitions: Definitions,
-    dialogue: HashMap<SceneSectionId, Dialogue>,
+    pub(crate) definitions: Definitions,
+    #[schemars(with = "std::collections::HashMap<SceneSectionId, Dialogue>")]
+    pub(crate) dialogue: HashMap<SceneSectionId, Dialogue>,
     #[serde(flatten)]
-    commands: Option<StaticCommands>, // TODO: execute these?
+    pub(crate) commands: Option<StaticCommands>, // TODO: execute these?
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+impl Scene {
+    pub fn id(&self) -> &SceneId {
+        &self.id
+    }
+
+    pub fn music(&self) -> Option<&str> {
+        self.music.as_deref()
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 pub struct Character {
     pub name: String,
     #[serde(default)]
     pub image: NpcImage,
     #[serde(default)]
     pub voice: NpcVoice,
+    pub level: Option<u32>,
+    #[serde(default)]
+    pub stats: Stats,
 }
 
 impl Default for Character {
@@ -86,77 +125,68 @@ impl Default for Character {
             name: "?".to_string(),
             image: NpcImage::default(),
             voice: NpcVoice::default(),
+            level: None,
+            stats: Stats::default(),
         }
     }
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub struct Dialogue {
     #[serde(default)]
-    lines: Vec<Line>,
+    pub(crate) lines: Vec<Line>,
     #[serde(alias = "resp", default)]
-    responses: Vec<Response>,
+    pub(crate) responses: Vec<Response>,
     #[serde(flatten)]
-    commands: Option<StaticCommands>,
+    pub(crate) commands: Option<StaticCommands>,
     #[serde(alias = "cont")]
-    continue_to: Option<SceneSectionId>,
+    pub(crate) continue_to: Option<SceneSectionId>,
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub struct Line {
     pub from: NpcId,
     #[serde(alias = "txt")]
     pub text: String,
     #[serde(flatten)]
-    commands: Option<StaticCommands>,
+    pub(crate) commands: Option<StaticCommands>,
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct Response {
     #[serde(alias = "txt")]
     pub text: String,
     #[serde(alias = "lnk")]
-    link: Option<SceneSectionId>,
-    skill_check: Option<SkillCheck>,
+    pub(crate) link: Option<SceneSectionId>,
+    pub(crate) skill_check: Option<SkillCheck>,
     #[serde(alias = "cond", default)]
-    conditions: Vec<Condition>,
+    pub(crate) conditions: Vec<Condition>,
     #[serde(flatten)]
-    commands: Option<StaticCommands>,
+    pub(crate) commands: Option<StaticCommands>,
 }
 impl Response {
-    fn evaluate_conditions(&self, scene_manager: &SceneManager) -> bool {
-        self.conditions.iter().all(|c| c.evaluate(scene_manager))
+    fn evaluate_conditions(&self, scene_manager: &SceneManager, inventory: &Inventory) -> bool {
+        self.conditions
+            .iter()
+            .all(|c| c.evaluate(scene_manager, inventory))
     }
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
 pub struct SkillCheck {
     #[serde(alias = "lnk-fail")]
-    link_fail: SceneSectionId,
+    pub(crate) link_fail: SceneSectionId,
     #[serde(alias = "lnk-crit-fail")]
-    link_crit_fail: Option<SceneSectionId>,
-    modifier: Option<i32>,
-    check: Skill,
+    pub(crate) link_crit_fail: Option<SceneSectionId>,
+    pub(crate) modifier: Option<i32>,
+    pub(crate) check: Skill,
 }
 
-// TODO: replace with actual skill enum from `components.rs` when that is added
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq, derive_more::From)]
-#[serde(rename_all = "lowercase")]
-pub enum Skill {
-    Strength,
-    Perception,
-    Endurance,
-    Charisma,
-    Intelligence,
-    Agility,
-    Luck,
-}
-
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case", tag = "type")]
 pub enum Condition {
     VarEquals {
@@ -172,20 +202,31 @@ pub enum Condition {
         #[serde(alias = "cond")]
         conditions: Vec<Condition>,
     },
-    HasItem,
+    HasItem {
+        item: ItemId,
+        #[serde(alias = "amount")]
+        count: Option<u32>,
+    },
     QuestStage,
 }
 impl Condition {
-    fn evaluate(&self, scene_manager: &SceneManager) -> bool {
+    fn evaluate(&self, scene_manager: &SceneManager, inventory: &Inventory) -> bool {
         match self {
             Condition::VarEquals { variable, value } => scene_manager
                 .get_variable(variable)
                 .map(|v| v == value)
                 .unwrap_or(false),
-            Condition::Any { conditions } => conditions.iter().any(|c| c.evaluate(scene_manager)),
-            Condition::Not { conditions } => conditions.iter().all(|c| !c.evaluate(scene_manager)),
-            Condition::HasItem => todo!(),
-            Condition::QuestStage => todo!(),
+            Condition::Any { conditions } => conditions
+                .iter()
+                .any(|c| c.evaluate(scene_manager, inventory)),
+            Condition::Not { conditions } => conditions
+                .iter()
+                .all(|c| !c.evaluate(scene_manager, inventory)),
+            Condition::HasItem { item, count } => inventory.has_item(item, count.unwrap_or(1)),
+            Condition::QuestStage => {
+                warn!("quest stage conditions are not implemented yet, treating them as unmet");
+                false
+            }
         }
     }
 }
@@ -197,23 +238,53 @@ pub struct ScenePlayer {
     current_line: usize,
     highlighted_response: usize,
     executed_commands: HashSet<SceneBookmark>,
+    visited: HashSet<SceneBookmark>,
 }
 
 impl ScenePlayer {
     fn new(scene: SceneId, start_key: Option<SceneSectionId>) -> Self {
-        Self {
+        let mut scene_player = Self {
             scene,
             current_key: start_key.unwrap_or_default(),
             current_line: 0,
             highlighted_response: 0,
             executed_commands: HashSet::default(),
-        }
+            visited: HashSet::default(),
+        };
+        scene_player.visit_line();
+        scene_player
+    }
+
+    pub fn scene(&self) -> &SceneId {
+        &self.scene
     }
 
     pub fn cleanup(mut commands: Commands) {
         commands.remove_resource::<Self>();
     }
 
+    /// Runs section commands and skips past dialogue without lines, so scenes
+    /// progress whether or not there is a UI showing them.
+    pub fn update(
+        scene_player: Option<ResMut<Self>>,
+        mut scene_manager: ResMut<SceneManager>,
+        mut end_scene_event: EventWriter<EndSceneEvent>,
+        mut scene_commands_event: EventWriter<StaticCommandsEvent>,
+    ) {
+        let Some(mut scene_player) = scene_player else {
+            return;
+        };
+        let dialogue = scene_player.get_dialogue(&scene_manager, &mut scene_commands_event);
+        if dialogue.lines.is_empty() {
+            scene_player.input(
+                ScenePlayerInput::SelectCurrent,
+                &mut scene_manager,
+                &mut end_scene_event,
+                &mut scene_commands_event,
+            );
+        }
+    }
+
     fn get_scene<'a>(&self, scene_manager: &'a SceneManager) -> &'a Scene {
         scene_manager.scenes.get(&self.scene).unwrap_or_else(|| {
             panic!("no scene with ID: {:?}", self.scene);
@@ -243,14 +314,23 @@ impl ScenePlayer {
         dialogue
     }
 
+    /// Marks the current section and line as reached.
+    fn visit_line(&mut self) {
+        let section = SceneBookmark::new(&self.scene, Some(&self.current_key), None, None);
+        self.visited.insert(section);
+        self.visited.insert(self.line_bookmark());
+    }
+
     fn reset_line(&mut self) {
         self.current_line = 0;
         self.highlighted_response = 0;
+        self.visit_line();
     }
 
     fn advance_line(&mut self) {
         self.current_line += 1;
         self.highlighted_response = 0;
+        self.visit_line();
     }
 
     fn set_key(&mut self, key: SceneSectionId) {
@@ -258,16 +338,41 @@ impl ScenePlayer {
         self.reset_line()
     }
 
+    /// Keeps the player's place in a scene that was reloaded, or goes back to
+    /// the scene's entry section if the current one was removed. Returns false,
+    /// without changing anything, if neither section exists.
+    fn resync(&mut self, scene: &Scene, entry: Option<&SceneSectionId>) -> bool {
+        if !scene.dialogue.contains_key(&self.current_key) {
+            let entry = entry.cloned().unwrap_or_default();
+            if !scene.dialogue.contains_key(&entry) {
+                return false;
+            }
+            warn!(
+                "section {:?} was removed from scene {:?}, continuing from {:?}",
+                self.current_key, self.scene, entry
+            );
+            self.set_key(entry);
+        }
+        let dialogue = &scene.dialogue[&self.current_key];
+        self.current_line = self
+            .current_line
+            .min(dialogue.lines.len().saturating_sub(1));
+        self.highlighted_response = self
+            .highlighted_response
+            .min(dialogue.responses.len().saturating_sub(1));
+        true
+    }
+
     fn select(
         &mut self,
         dialogue: &Dialogue,
         end_scene_event: &mut EventWriter<EndSceneEvent>,
         scene_commands_events: &mut EventWriter<StaticCommandsEvent>,
-    ) {
+    ) -> Option<String> {
         if dialogue.lines.is_empty() {
             info!("ending scene!"); // TODO: delete
             end_scene_event.write(EndSceneEvent);
-            return;
+            return None;
         }
 
         let line = dialogue
@@ -296,6 +401,12 @@ impl ScenePlayer {
         // execute response
         else if let Some(response) = dialogue.responses.get(self.highlighted_response) {
             info!("execute response"); // TODO: delete
+            self.visited.insert(SceneBookmark::new(
+                &self.scene,
+                Some(&self.current_key),
+                None,
+                Some(self.highlighted_response),
+            ));
             if let Some(commands) = response.commands.clone() {
                 let bookmark = SceneBookmark::new(
                     &self.scene,
@@ -316,6 +427,7 @@ impl ScenePlayer {
                 info!("ending scene!"); // TODO: delete
                 end_scene_event.write(EndSceneEvent);
             }
+            return Some(response.text.clone());
         // continue to next section
         } else if let Some(ref section) = dialogue.continue_to {
             info!("continuing to next section"); // TODO: delete
@@ -326,11 +438,13 @@ impl ScenePlayer {
             info!("ending scene!"); // TODO: delete
             end_scene_event.write(EndSceneEvent);
         }
+        None
     }
 
     pub fn get_current<'a>(
         &'a self,
         scene_manager: &'a SceneManager,
+        player_inventory: &Inventory,
         scene_commands_events: &mut EventWriter<StaticCommandsEvent>,
     ) -> Option<UiScenePart<'a>> {
         let dialogue = self.get_dialogue(scene_manager, scene_commands_events);
@@ -349,7 +463,7 @@ impl ScenePlayer {
                     dialogue
                         .responses
                         .iter()
-                        .filter(|resp| resp.evaluate_conditions(scene_manager))
+                        .filter(|resp| resp.evaluate_conditions(scene_manager, player_inventory))
                         .collect(),
                 )
             }
@@ -357,13 +471,14 @@ impl ScenePlayer {
         Some(UiScenePart { line, responses })
     }
 
+    /// Applies `input`, returning the text of the response it chose, if any.
     pub fn input(
         &mut self,
         input: ScenePlayerInput,
         scene_manager: &mut SceneManager,
         end_scene_event: &mut EventWriter<EndSceneEvent>,
         scene_commands_events: &mut EventWriter<StaticCommandsEvent>,
-    ) {
+    ) -> Option<String> {
         let dialogue = self.get_dialogue(scene_manager, scene_commands_events);
         match input {
             ScenePlayerInput::MoveUp => {
@@ -378,12 +493,69 @@ impl ScenePlayer {
             ScenePlayerInput::MoveTo(i) => self.highlighted_response = i,
             ScenePlayerInput::Select(i) => {
                 self.highlighted_response = i;
-                self.select(dialogue, end_scene_event, scene_commands_events);
+                return self.select(dialogue, end_scene_event, scene_commands_events);
             }
-            ScenePlayerInput::Select(_) | ScenePlayerInput::SelectCurrent => {
-                self.select(dialogue, end_scene_event, scene_commands_events);
+            ScenePlayerInput::SelectCurrent => {
+                return self.select(dialogue, end_scene_event, scene_commands_events);
             }
         }
+        None
+    }
+
+    /// The line currently on screen, without running any commands.
+    pub fn current_line<'a>(&self, scene_manager: &'a SceneManager) -> Option<&'a Line> {
+        scene_manager
+            .scenes
+            .get(&self.scene)?
+            .dialogue
+            .get(&self.current_key)?
+            .lines
+            .get(self.current_line)
+    }
+
+    /// Whether the current line is the last one and is followed by responses
+    /// the player has to choose between.
+    pub fn awaiting_response(&self, scene_manager: &SceneManager) -> bool {
+        scene_manager
+            .scenes
+            .get(&self.scene)
+            .and_then(|scene| scene.dialogue.get(&self.current_key))
+            .is_some_and(|dialogue| {
+                !dialogue.responses.is_empty() && self.current_line + 1 >= dialogue.lines.len()
+            })
+    }
+
+    /// Indices of the responses the player can pick right now, or nothing if
+    /// the current line is not followed by responses.
+    pub fn available_responses(
+        &self,
+        scene_manager: &SceneManager,
+        player_inventory: &Inventory,
+    ) -> Vec<usize> {
+        if !self.awaiting_response(scene_manager) {
+            return Vec::new();
+        }
+        scene_manager.scenes[&self.scene].dialogue[&self.current_key]
+            .responses
+            .iter()
+            .enumerate()
+            .filter(|(_, response)| response.evaluate_conditions(scene_manager, player_inventory))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    pub fn line_bookmark(&self) -> SceneBookmark {
+        SceneBookmark::new(
+            &self.scene,
+            Some(&self.current_key),
+            Some(self.current_line),
+            None,
+        )
+    }
+
+    /// Sections, lines and responses reached since the scene started.
+    pub fn visited(&self) -> &HashSet<SceneBookmark> {
+        &self.visited
     }
 
     pub fn highlighted_response(&self) -> usize {
@@ -395,11 +567,10 @@ impl ScenePlayer {
         bookmark: SceneBookmark,
         commands: StaticCommands,
         scene_manager: &mut SceneManager,
-        start_battle_event: &mut EventWriter<StartBattleEvent>,
-        update_npc_event: &mut EventWriter<UpdateNpcEvent>,
+        writers: &mut StaticCommandsWriters,
     ) {
         if self.executed_commands.insert(bookmark) {
-            commands.execute(scene_manager, start_battle_event, update_npc_event);
+            commands.execute(scene_manager, writers);
         }
     }
 }
@@ -423,6 +594,10 @@ pub struct SceneManager {
     pub(crate) scenes: HashMap<SceneId, Scene>,
     pub(crate) variables: HashMap<String, String>,
     pub(crate) entries: HashMap<SceneId, SceneSectionId>,
+    /// Which scene each loaded scene asset defined, so it can be unloaded.
+    assets: HashMap<AssetId<Scene>, SceneId>,
+    /// The asset pack each scene was defined by.
+    origins: HashMap<SceneId, usize>,
 }
 
 impl SceneManager {
@@ -432,8 +607,13 @@ impl SceneManager {
 
     pub fn load_scene<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<()> {
         info!("loading scene: {:?}", path.as_ref());
-        let scene_json = std::fs::read_to_string(path)?;
-        let scene: Scene = serde_json::from_str(&scene_json)?;
+        let scene: Scene = read_data(path)?;
+        if self.scenes.contains_key(&scene.id) {
+            warn!(
+                "scene {:?} is defined more than once, replacing it",
+                scene.id
+            );
+        }
         self.scenes.insert(scene.id.clone(), scene);
         Ok(())
     }
@@ -444,21 +624,11 @@ impl SceneManager {
     }
 
     pub fn load_folder<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<()> {
-        path.as_ref()
-            .read_dir()?
-            .filter_map(Result::ok)
-            .filter(|entry| {
-                entry
-                    .path()
-                    .extension()
-                    .map(|ext| ext == "json")
-                    .unwrap_or(false)
-            })
-            .for_each(|entry| {
-                if let Err(e) = self.load_scene(entry.path()) {
-                    error!("failed to load scene: {e}");
-                }
-            });
+        utils::data_files(path)?.into_iter().for_each(|path| {
+            if let Err(e) = self.load_scene(&path) {
+                error!("failed to load scene: {}", LoadDiagnostic::new(&path, &e));
+            }
+        });
         Ok(())
     }
 
@@ -467,13 +637,93 @@ impl SceneManager {
         Ok(self)
     }
 
+    /// Keeps the manager in sync with the scenes loaded by the asset server.
+    /// Scenes from later asset packs override earlier ones. The scene being
+    /// played is only replaced if the player's place in it still exists.
+    pub fn handle_asset_events(
+        mut scene_manager: ResMut<Self>,
+        mut asset_events: EventReader<AssetEvent<Scene>>,
+        scenes: Res<Assets<Scene>>,
+        asset_server: Res<AssetServer>,
+        packs: Res<AssetPacks>,
+        mut scene_player: Option<ResMut<ScenePlayer>>,
+    ) {
+        for event in asset_events.read() {
+            match event {
+                AssetEvent::Added { id } | AssetEvent::Modified { id } => {
+                    let Some(scene) = scenes.get(*id) else {
+                        continue;
+                    };
+                    let priority = asset_server
+                        .get_path(*id)
+                        .map(|path| packs.priority(path.source()))
+                        .unwrap_or_default();
+                    // a reloaded file doesn't conflict with its old version
+                    let reloaded = scene_manager.assets.get(id) == Some(&scene.id);
+                    let current = scene_manager
+                        .origins
+                        .get(&scene.id)
+                        .copied()
+                        .filter(|_| !reloaded);
+                    if !packs.resolve("scene", &scene.id, priority, current) {
+                        continue;
+                    }
+                    let playing = scene_player
+                        .as_deref_mut()
+                        .filter(|scene_player| scene_player.scene == scene.id);
+                    if let Some(scene_player) = playing {
+                        let entry = scene_manager.entries.get(&scene.id);
+                        if !scene_player.resync(scene, entry) {
+                            warn!(
+                                "reloaded scene {:?} has nowhere to continue from, keeping the old version",
+                                scene.id
+                            );
+                            continue;
+                        }
+                    }
+                    info!("loaded scene: {:?}", scene.id);
+                    scene_manager
+                        .assets
+                        .retain(|_, scene_id| *scene_id != scene.id);
+                    scene_manager.assets.insert(*id, scene.id.clone());
+                    scene_manager.origins.insert(scene.id.clone(), priority);
+                    scene_manager.scenes.insert(scene.id.clone(), scene.clone());
+                }
+                AssetEvent::Removed { id } => {
+                    let Some(scene_id) = scene_manager.assets.remove(id) else {
+                        continue;
+                    };
+                    if scene_player
+                        .as_deref()
+                        .is_some_and(|scene_player| scene_player.scene == scene_id)
+                    {
+                        warn!(
+                            "scene {scene_id:?} was removed while playing, keeping it until it ends"
+                        );
+                        continue;
+                    }
+                    scene_manager.origins.remove(&scene_id);
+                    scene_manager.scenes.remove(&scene_id);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn get_scene(&self, id: &SceneId) -> Option<&Scene> {
+        self.scenes.get(id)
+    }
+
     pub fn play_scene(
         &self,
         scene_id: SceneId,
         spawn_npc_event: &mut EventWriter<SpawnNpcEvent>,
+        spawn_vendor_event: &mut EventWriter<SpawnVendorEvent>,
     ) -> Option<ScenePlayer> {
         self.scenes.contains_key(&scene_id).then(|| {
-            self.scenes[&scene_id].definitions.create(spawn_npc_event);
+            self.scenes[&scene_id]
+                .definitions
+                .create(spawn_npc_event, spawn_vendor_event);
             let scene_entry = self.entries.get(&scene_id);
             ScenePlayer::new(scene_id, scene_entry.cloned())
         })
@@ -486,7 +736,7 @@ impl SceneManager {
         self.variables.extend(variables)
     }
 
-    fn get_variable(&self, variable: &str) -> Option<&String> {
+    pub(crate) fn get_variable(&self, variable: &str) -> Option<&String> {
         self.variables.get(variable)
     }
 
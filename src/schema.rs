@@ -0,0 +1,315 @@
+use std::{collections::BTreeSet, path::Path};
+
+use schemars::{
+    JsonSchema,
+    r#gen::{SchemaGenerator, SchemaSettings},
+    schema::{ObjectValidation, Schema, SchemaObject},
+    visit::{self, Visitor},
+};
+
+use crate::{
+    Condition, Dialogue, GameManifest, GameStart, ItemFile, Line, Map, MapAction, Response, Scene,
+    StaticCommands,
+};
+
+/// Short names the data files may use in place of a field, as
+/// `(field, alias)`. schemars doesn't read `#[serde(alias)]`, so these have to
+/// be kept in step with the types by hand.
+pub const FIELD_ALIASES: [(&str, &str); 20] = [
+    ("text", "txt"),
+    ("link", "lnk"),
+    ("responses", "resp"),
+    ("conditions", "cond"),
+    ("condition", "cond"),
+    ("continue-to", "cont"),
+    ("link_fail", "lnk-fail"),
+    ("link_crit_fail", "lnk-crit-fail"),
+    ("variable", "var"),
+    ("variables", "vars"),
+    ("count", "amount"),
+    ("loot_table", "loot-table"),
+    ("slot", "limb"),
+    ("hp", "heal_HP"),
+    ("position", "pos"),
+    ("range", "pos-range"),
+    ("player-position", "player-pos"),
+    ("daily-gold", "daily_gold"),
+    ("item-count", "item_count"),
+    ("item-rarity", "item_rarity"),
+];
+
+/// Adds every alias in [`FIELD_ALIASES`] next to its field. A required field
+/// stops being required on its own, and the object instead requires either
+/// the field or its alias.
+#[derive(Debug, Clone)]
+struct AliasVisitor;
+
+impl Visitor for AliasVisitor {
+    fn visit_schema_object(&mut self, schema: &mut SchemaObject) {
+        visit::visit_schema_object(self, schema);
+
+        let Some(object) = schema.object.as_deref_mut() else {
+            return;
+        };
+        let mut either_required = Vec::new();
+        for (field, alias) in FIELD_ALIASES {
+            if object.properties.contains_key(alias) {
+                continue;
+            }
+            let Some(property) = object.properties.get(field).cloned() else {
+                continue;
+            };
+            object.properties.insert(alias.to_string(), property);
+            if object.required.remove(field) {
+                either_required.push(one_of_required(field, alias));
+            }
+        }
+        if !either_required.is_empty() {
+            schema
+                .subschemas()
+                .all_of
+                .get_or_insert_with(Vec::new)
+                .extend(either_required);
+        }
+    }
+}
+
+/// A schema requiring `field`, `alias`, or both.
+fn one_of_required(field: &str, alias: &str) -> Schema {
+    let required = |name: &str| {
+        Schema::Object(SchemaObject {
+            object: Some(Box::new(ObjectValidation {
+                required: [name.to_string()].into(),
+                ..Default::default()
+            })),
+            ..Default::default()
+        })
+    };
+    let mut schema = SchemaObject::default();
+    schema.subschemas().any_of = Some(vec![required(field), required(alias)]);
+    Schema::Object(schema)
+}
+
+fn schema_generator() -> SchemaGenerator {
+    SchemaSettings::draft07()
+        .with_visitor(AliasVisitor)
+        .into_generator()
+}
+
+/// Generates the JSON Schema of every data file type, and of the parts of a
+/// scene editors most often want to check on their own, as `(name, schema)`.
+pub fn schemas() -> Vec<(&'static str, serde_json::Value)> {
+    fn schema<T: JsonSchema>(name: &'static str) -> (&'static str, serde_json::Value) {
+        let schema = schema_generator().into_root_schema_for::<T>();
+        let value = serde_json::to_value(schema).expect("schemas serialize to JSON");
+        (name, value)
+    }
+
+    vec![
+        schema::<Scene>("scene"),
+        schema::<Dialogue>("dialogue"),
+        schema::<Line>("line"),
+        schema::<Response>("response"),
+        schema::<Condition>("condition"),
+        schema::<StaticCommands>("static-commands"),
+        schema::<ItemFile>("items"),
+        schema::<Map>("map"),
+        schema::<MapAction>("map-action"),
+        schema::<GameManifest>("game"),
+        schema::<GameStart>("save"),
+    ]
+}
+
+/// Every field name the data files can use, aliases included.
+pub fn known_fields() -> BTreeSet<String> {
+    fn collect(value: &serde_json::Value, fields: &mut BTreeSet<String>) {
+        match value {
+            serde_json::Value::Object(object) => {
+                if let Some(serde_json::Value::Object(properties)) = object.get("properties") {
+                    fields.extend(properties.keys().cloned());
+                }
+                object.values().for_each(|value| collect(value, fields));
+            }
+            serde_json::Value::Array(values) => {
+                values.iter().for_each(|value| collect(value, fields))
+            }
+            _ => {}
+        }
+    }
+
+    let mut fields = BTreeSet::new();
+    for (_, schema) in schemas() {
+        collect(&schema, &mut fields);
+    }
+    fields
+}
+
+/// Writes every schema from [`schemas`] to `dir` as `<name>.schema.json`.
+pub fn export_schemas(dir: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    for (name, schema) in schemas() {
+        let path = dir.join(format!("{name}.schema.json"));
+        std::fs::write(&path, serde_json::to_string_pretty(&schema)? + "\n")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeSet, fmt::Debug, path::Path};
+
+    use serde::de::DeserializeOwned;
+
+    use crate::{
+        Condition, Dialogue, FIELD_ALIASES, GameStart, ItemFile, Line, Map, MapAction, Response,
+        StaticCommands, Vendor, schemas,
+    };
+
+    /// Reads `json` as `T` once as written and once with `field` renamed to
+    /// `alias`, and checks both read the same.
+    fn reads_alias<T: DeserializeOwned + PartialEq + Debug>(
+        field: &'static str,
+        alias: &'static str,
+        json: &str,
+    ) -> (&'static str, &'static str) {
+        let key = format!("\"{field}\":");
+        assert!(json.contains(&key), "`{field}` is not in: {json}");
+        let aliased = json.replace(&key, &format!("\"{alias}\":"));
+        let expected: T = serde_json::from_str(json).unwrap();
+        let actual: T = serde_json::from_str(&aliased)
+            .unwrap_or_else(|e| panic!("`{alias}` for `{field}` does not read: {e}"));
+        assert_eq!(
+            actual, expected,
+            "`{alias}` reads differently from `{field}`"
+        );
+        (field, alias)
+    }
+
+    #[test]
+    fn scene_schema_accepts_aliases() {
+        let (_, scene) = schemas()
+            .into_iter()
+            .find(|(name, _)| *name == "scene")
+            .unwrap();
+        let json = scene.to_string();
+        for alias in ["txt", "lnk", "resp", "cond"] {
+            assert!(json.contains(&format!("\"{alias}\"")), "missing `{alias}`");
+        }
+    }
+
+    #[test]
+    fn field_aliases_read_as_their_fields() {
+        let map = r#"{
+            "id": "test",
+            "layers": { "ground": "ground.png", "base": "base.png" },
+            "player-position": { "x": 1, "y": 2 },
+            "actions": []
+        }"#;
+        let skill_check = r#"{
+            "text": "Lift it",
+            "skill-check": { "link_fail": "drop", "link_crit_fail": "hurt", "check": "strength" }
+        }"#;
+        let checked: BTreeSet<_> = [
+            reads_alias::<Line>("text", "txt", r#"{ "from": "mike", "text": "Hi" }"#),
+            reads_alias::<Response>("link", "lnk", r#"{ "text": "Bye", "link": "end" }"#),
+            reads_alias::<Dialogue>("responses", "resp", r#"{ "responses": [{ "text": "Bye" }] }"#),
+            reads_alias::<Response>(
+                "conditions",
+                "cond",
+                r#"{ "text": "Bye", "conditions": [{ "type": "quest-stage" }] }"#,
+            ),
+            reads_alias::<Condition>(
+                "conditions",
+                "cond",
+                r#"{ "type": "not", "conditions": [{ "type": "quest-stage" }] }"#,
+            ),
+            reads_alias::<MapAction>(
+                "condition",
+                "cond",
+                r#"{ "position": { "x": 1, "y": 2 }, "name": "Door", "condition": true }"#,
+            ),
+            reads_alias::<Dialogue>("continue-to", "cont", r#"{ "continue-to": "next" }"#),
+            reads_alias::<Response>("link_fail", "lnk-fail", skill_check),
+            reads_alias::<Response>("link_crit_fail", "lnk-crit-fail", skill_check),
+            reads_alias::<Condition>(
+                "variable",
+                "var",
+                r#"{ "type": "var-equals", "variable": "met", "value": "yes" }"#,
+            ),
+            reads_alias::<StaticCommands>("variables", "vars", r#"{ "variables": { "met": "yes" } }"#),
+            reads_alias::<GameStart>("variables", "vars", r#"{ "variables": { "met": "yes" } }"#),
+            reads_alias::<Condition>(
+                "count",
+                "amount",
+                r#"{ "type": "has-item", "item": "apple", "count": 2 }"#,
+            ),
+            reads_alias::<StaticCommands>(
+                "count",
+                "amount",
+                r#"{ "reward-item": { "item": "apple", "count": 2 } }"#,
+            ),
+            reads_alias::<StaticCommands>(
+                "count",
+                "amount",
+                r#"{ "remove-item": { "item": "apple", "count": 2 } }"#,
+            ),
+            reads_alias::<ItemFile>("loot_table", "loot-table", r#"{ "loot_table": [] }"#),
+            reads_alias::<ItemFile>(
+                "slot",
+                "limb",
+                r#"{ "apparel": [{
+                    "id": "hat", "name": "Hat", "slot": "head", "defense": 1, "weight": 1, "value": 5
+                }] }"#,
+            ),
+            reads_alias::<ItemFile>(
+                "hp",
+                "heal_HP",
+                r#"{ "food": [{ "id": "apple", "name": "Apple", "hp": 5, "weight": 1, "value": 2 }] }"#,
+            ),
+            reads_alias::<GameStart>("position", "pos", r#"{ "position": { "x": 1, "y": 2 } }"#),
+            reads_alias::<MapAction>(
+                "position",
+                "pos",
+                r#"{ "position": { "x": 1, "y": 2 }, "name": "Door" }"#,
+            ),
+            reads_alias::<MapAction>(
+                "range",
+                "pos-range",
+                r#"{ "range": { "start": { "x": 1, "y": 2 }, "end": { "x": 3, "y": 4 } }, "name": "Door" }"#,
+            ),
+            reads_alias::<Map>("player-position", "player-pos", map),
+            reads_alias::<Vendor>("daily-gold", "daily_gold", r#"{ "name": "Shop", "daily-gold": 5 }"#),
+            reads_alias::<Vendor>("item-count", "item_count", r#"{ "name": "Shop", "item-count": 5 }"#),
+            reads_alias::<Vendor>(
+                "item-rarity",
+                "item_rarity",
+                r#"{ "name": "Shop", "item-rarity": "rare" }"#,
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        for (field, alias) in FIELD_ALIASES {
+            assert!(
+                checked.contains(&(field, alias)),
+                "`{alias}` for `{field}` is untested"
+            );
+        }
+    }
+
+    #[test]
+    fn exported_schemas_are_current() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("schemas");
+        for (name, schema) in schemas() {
+            let path = dir.join(format!("{name}.schema.json"));
+            let exported = std::fs::read_to_string(&path)
+                .unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+            let exported: serde_json::Value = serde_json::from_str(&exported).unwrap();
+            assert!(
+                exported == schema,
+                "{} is out of date, run `cargo run -- --export-schemas`",
+                path.display()
+            );
+        }
+    }
+}
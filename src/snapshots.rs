@@ -0,0 +1,220 @@
+use std::{fmt::Write, path::Path};
+
+use crate::{
+    DialogueCoverage, HistoryEntry, ItemManager, SceneId, SceneManager, ScenePlayerInput,
+    SceneRunner,
+};
+
+/// Responses chosen along a single path before it is cut off.
+const MAX_CHOICES: usize = 6;
+/// Inputs along a single path before it is cut off, so loops terminate.
+const MAX_STEPS: usize = 64;
+/// Paths recorded per scene.
+const MAX_PATHS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ending {
+    Finished,
+    ChoiceLimit,
+    StepLimit,
+}
+
+struct Explorer<'a> {
+    item_manager: &'a ItemManager,
+    scene_manager: &'a SceneManager,
+    scene_id: &'a SceneId,
+    paths: Vec<(Vec<usize>, String)>,
+    coverage: &'a mut DialogueCoverage,
+}
+
+impl Explorer<'_> {
+    /// Plays the scene choosing `prefix`, then branches on every response
+    /// available at the next choice.
+    fn explore(&mut self, prefix: Vec<usize>) -> anyhow::Result<()> {
+        if self.paths.len() >= MAX_PATHS {
+            return Ok(());
+        }
+        let mut runner =
+            SceneRunner::with_managers(self.item_manager.clone(), self.scene_manager.clone(), 0);
+        runner.play(self.scene_id)?;
+
+        let mut choices = prefix.iter();
+        let mut steps = 0;
+        let ending = loop {
+            if !runner.is_playing() {
+                break Ending::Finished;
+            }
+            if steps >= MAX_STEPS {
+                break Ending::StepLimit;
+            }
+            steps += 1;
+            match runner.available_responses() {
+                Some(available) if !available.is_empty() => match choices.next() {
+                    Some(&choice) => runner.input(ScenePlayerInput::Select(choice))?,
+                    None if prefix.len() >= MAX_CHOICES => break Ending::ChoiceLimit,
+                    None => {
+                        for choice in available {
+                            let mut path = prefix.clone();
+                            path.push(choice);
+                            self.explore(path)?;
+                        }
+                        return Ok(());
+                    }
+                },
+                _ => runner.input(ScenePlayerInput::SelectCurrent)?,
+            }
+        };
+
+        self.coverage.merge(runner.coverage());
+        let text = transcript(&runner, &prefix, ending);
+        self.paths.push((prefix, text));
+        Ok(())
+    }
+}
+
+fn transcript(runner: &SceneRunner, path: &[usize], ending: Ending) -> String {
+    let run = runner.result();
+    let path: Vec<_> = path.iter().map(usize::to_string).collect();
+    let mut text = format!("## path: [{}]\n", path.join(", "));
+    for entry in &run.transcript {
+        match entry {
+            HistoryEntry::Line {
+                speaker,
+                text: line,
+                ..
+            } if speaker.is_empty() => writeln!(text, "{line}").unwrap(),
+            HistoryEntry::Line {
+                speaker,
+                text: line,
+                ..
+            } => writeln!(text, "{speaker}: {line}").unwrap(),
+            HistoryEntry::Response { text: response, .. } => {
+                writeln!(text, "> {response}").unwrap()
+            }
+        }
+    }
+    for (bookmark, _commands) in &run.commands {
+        writeln!(text, "[commands] {bookmark}").unwrap();
+    }
+    for npc_id in &run.battles {
+        writeln!(text, "[battle] {npc_id}").unwrap();
+    }
+    let mut variables: Vec<_> = run
+        .variables
+        .iter()
+        .map(|(variable, value)| format!("{variable}={value}"))
+        .collect();
+    variables.sort();
+    if !variables.is_empty() {
+        writeln!(text, "[variables] {}", variables.join(", ")).unwrap();
+    }
+    let mut entries: Vec<_> = run
+        .entries
+        .iter()
+        .map(|(scene, section)| format!("{scene}={section}"))
+        .collect();
+    entries.sort();
+    if !entries.is_empty() {
+        writeln!(text, "[entries] {}", entries.join(", ")).unwrap();
+    }
+    let ending = match ending {
+        Ending::Finished => "[end]",
+        Ending::ChoiceLimit => "[stopped: choice limit]",
+        Ending::StepLimit => "[stopped: step limit]",
+    };
+    writeln!(text, "{ending}").unwrap();
+    text
+}
+
+/// Plays every response path through a scene, up to a bounded depth, and
+/// returns their transcripts as one snapshot. Everything the paths reached is
+/// added to `coverage`.
+pub fn scene_snapshot(
+    item_manager: &ItemManager,
+    scene_manager: &SceneManager,
+    scene_id: &SceneId,
+    coverage: &mut DialogueCoverage,
+) -> anyhow::Result<String> {
+    let mut explorer = Explorer {
+        item_manager,
+        scene_manager,
+        scene_id,
+        paths: Vec::new(),
+        coverage,
+    };
+    explorer.explore(Vec::new())?;
+    explorer.paths.sort();
+
+    let mut snapshot = format!("# scene: {scene_id}\n");
+    for (_path, text) in &explorer.paths {
+        snapshot.push('\n');
+        snapshot.push_str(text);
+    }
+    if explorer.paths.len() >= MAX_PATHS {
+        snapshot.push_str("\n# more paths omitted\n");
+    }
+    Ok(snapshot)
+}
+
+/// Compares every scene under `assets_path` against its snapshot in
+/// `snapshots_path`, returning the scenes that changed or have no snapshot.
+/// When `bless` is set, those snapshots are written instead. Everything the
+/// snapshots reached is added to `coverage`.
+pub fn check_snapshots(
+    assets_path: &Path,
+    snapshots_path: &Path,
+    bless: bool,
+    coverage: &mut DialogueCoverage,
+) -> anyhow::Result<Vec<SceneId>> {
+    let item_manager = ItemManager::new().with_load_folder(assets_path.join("items"))?;
+    let scene_manager = SceneManager::new().with_load_folder(assets_path.join("scenes"))?;
+    std::fs::create_dir_all(snapshots_path)?;
+
+    let mut scene_ids: Vec<_> = scene_manager.scenes.keys().cloned().collect();
+    scene_ids.sort_by_key(|scene_id| scene_id.0.clone());
+
+    let mut changed = Vec::new();
+    for scene_id in scene_ids {
+        let snapshot = scene_snapshot(&item_manager, &scene_manager, &scene_id, coverage)?;
+        let path = snapshots_path.join(&scene_id.0).with_extension("txt");
+        match std::fs::read_to_string(&path) {
+            Ok(existing) if existing == snapshot => {}
+            _ if bless => std::fs::write(&path, snapshot)?,
+            Ok(_) => changed.push(scene_id),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => changed.push(scene_id),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::{DialogueCoverage, check_snapshots};
+
+    /// Run with `BLESS_SNAPSHOTS=1` (or `--bless-snapshots`) to accept changes,
+    /// and with `DIALOGUE_COVERAGE=<file>` to add what the snapshots reached to
+    /// a coverage file.
+    #[test]
+    fn scene_transcripts_match_snapshots() {
+        let bless = std::env::var_os("BLESS_SNAPSHOTS").is_some();
+        let mut coverage = DialogueCoverage::default();
+        let changed = check_snapshots(
+            Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/assets")),
+            Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots/scenes")),
+            bless,
+            &mut coverage,
+        )
+        .unwrap();
+        // see `--coverage-report`
+        if let Some(coverage_path) = std::env::var_os("DIALOGUE_COVERAGE") {
+            coverage.merge_into_file(coverage_path).unwrap();
+        }
+        assert!(
+            changed.is_empty(),
+            "scene transcripts changed or have no snapshot: {changed:?}. rerun with BLESS_SNAPSHOTS=1 and review the diff"
+        );
+    }
+}
@@ -1,78 +1,268 @@
 use std::path::PathBuf;
 
-use bevy::{log::info, platform::collections::HashMap, prelude::EventWriter};
+use bevy::{
+    ecs::system::SystemParam,
+    log::{info, warn},
+    platform::collections::HashMap,
+    prelude::EventWriter,
+};
+use rand_core::RngCore;
+use schemars::JsonSchema;
 use serde::Deserialize;
 
 use crate::{
-    Character, NpcId, SceneId, SceneManager, SceneSectionId, SpawnNpcEvent, StartBattleEvent, TODO,
+    Character, ItemId, ItemInstance, ItemManager, LootTableId, NpcId, PlaySoundEvent, Rarity,
+    RemoveItemEvent, RewardExperienceEvent, RewardItemEvent, RewardLootEvent, SceneId,
+    SceneManager, SceneSectionId, SpawnNpcEvent, SpawnVendorEvent, StartBattleEvent, TODO,
     UpdateNpcEvent,
 };
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq, derive_more::From)]
+/// Event writers used by [`StaticCommands::execute`].
+#[derive(SystemParam)]
+pub struct StaticCommandsWriters<'w> {
+    pub start_battle: EventWriter<'w, StartBattleEvent>,
+    pub update_npc: EventWriter<'w, UpdateNpcEvent>,
+    pub reward_item: EventWriter<'w, RewardItemEvent>,
+    pub reward_loot: EventWriter<'w, RewardLootEvent>,
+    pub remove_item: EventWriter<'w, RemoveItemEvent>,
+    pub reward_experience: EventWriter<'w, RewardExperienceEvent>,
+    pub play_sound: EventWriter<'w, PlaySoundEvent>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq, derive_more::From)]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
 pub struct Definitions {
-    characters: Option<HashMap<NpcId, Character>>,
-    vendors: Option<TODO>,
-    quests: Option<TODO>,
+    #[schemars(with = "Option<std::collections::HashMap<NpcId, Character>>")]
+    pub(crate) characters: Option<HashMap<NpcId, Character>>,
+    #[schemars(with = "Option<std::collections::HashMap<VendorId, Vendor>>")]
+    vendors: Option<HashMap<VendorId, Vendor>>,
+    pub(crate) quests: Option<TODO>,
 }
 
 impl Definitions {
-    pub fn create(&self, spawn_npc_event: &mut EventWriter<SpawnNpcEvent>) {
+    pub fn create(
+        &self,
+        spawn_npc_event: &mut EventWriter<SpawnNpcEvent>,
+        spawn_vendor_event: &mut EventWriter<SpawnVendorEvent>,
+    ) {
         if let Some(characters) = &self.characters {
             for (character_id, character) in characters.iter() {
                 spawn_npc_event.write(SpawnNpcEvent(character_id.to_owned(), character.to_owned()));
             }
         }
-        if let Some(_vendors) = &self.vendors {
-            todo!()
+        if let Some(vendors) = &self.vendors {
+            for (vendor_id, vendor) in vendors.iter() {
+                spawn_vendor_event.write(SpawnVendorEvent(vendor_id.to_owned(), vendor.to_owned()));
+            }
         }
-        if let Some(_quests) = &self.quests {
-            todo!()
+        if self.quests.is_some() {
+            warn!("quests are not implemented yet, skipping them");
         }
     }
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(
+    Deserialize,
+    JsonSchema,
+    Debug,
+    Hash,
+    Clone,
+    PartialEq,
+    Eq,
+    derive_more::From,
+    derive_more::Display,
+)]
+pub struct VendorId(pub String);
+
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Vendor {
+    name: String,
+    music: Option<String>,
+    #[serde(alias = "daily_gold")]
+    daily_gold: Option<u32>,
+    #[serde(default)]
+    preset_inventory: Vec<ItemId>,
+    loot_table: Option<LootTableId>,
+    #[serde(alias = "item_count")]
+    item_count: Option<u32>,
+    #[serde(alias = "item_rarity")]
+    item_rarity: Option<Rarity>,
+}
+
+impl Vendor {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn daily_gold(&self) -> u32 {
+        self.daily_gold.unwrap_or(0)
+    }
+
+    /// Spawns the vendor's preset inventory plus `item-count` rolls of its loot
+    /// table, which defaults to the table with the vendor's own ID.
+    pub fn roll_inventory<R: RngCore + ?Sized>(
+        &self,
+        vendor_id: &VendorId,
+        item_manager: &ItemManager,
+        rng: &mut R,
+    ) -> Vec<ItemInstance> {
+        let loot_table = self
+            .loot_table
+            .clone()
+            .unwrap_or_else(|| LootTableId(vendor_id.0.clone()));
+        let mut inventory: Vec<ItemInstance> = self
+            .preset_inventory
+            .iter()
+            .filter_map(|item_id| item_manager.spawn(item_id.to_owned()))
+            .collect();
+        for _ in 0..self.item_count.unwrap_or(0) {
+            inventory.extend(item_manager.roll_loot(&loot_table, self.item_rarity, rng));
+        }
+        inventory
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 pub struct CharacterUpdate {
     pub name: Option<String>,
     pub image: Option<PathBuf>,
     pub voice: Option<String>,
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq, derive_more::From)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq, derive_more::From)]
 #[serde(deny_unknown_fields)]
 pub struct RewardGoldCommand {
     amount: u32,
     from: NpcId,
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq, derive_more::From)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq, derive_more::From)]
+#[serde(deny_unknown_fields)]
+pub struct RewardExperienceCommand {
+    amount: u32,
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum RewardItemCommand {
+    Item(ItemId),
+    Stack {
+        item: ItemId,
+        #[serde(alias = "amount")]
+        count: u32,
+    },
+}
+
+impl RewardItemCommand {
+    pub fn item(&self) -> &ItemId {
+        match self {
+            RewardItemCommand::Item(item) => item,
+            RewardItemCommand::Stack { item, .. } => item,
+        }
+    }
+
+    pub fn count(&self) -> u32 {
+        match self {
+            RewardItemCommand::Item(_) => 1,
+            RewardItemCommand::Stack { count, .. } => *count,
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum PlaySoundCommand {
+    Sound(String),
+    WithVolume {
+        sound: String,
+        /// In percent of full volume.
+        volume: Option<u32>,
+    },
+}
+
+impl PlaySoundCommand {
+    pub fn sound(&self) -> &str {
+        match self {
+            PlaySoundCommand::Sound(sound) => sound,
+            PlaySoundCommand::WithVolume { sound, .. } => sound,
+        }
+    }
+
+    pub fn volume(&self) -> Option<f32> {
+        match self {
+            PlaySoundCommand::Sound(_) => None,
+            PlaySoundCommand::WithVolume { volume, .. } => {
+                volume.map(|percent| percent as f32 / 100.0)
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RemoveItemCommand {
+    pub(crate) item: ItemId,
+    #[serde(alias = "amount")]
+    count: Option<u32>,
+    method: Option<TODO>,
+    recipient: Option<NpcId>,
+    show_message: Option<TODO>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone, Default, PartialEq, Eq, derive_more::From)]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
 pub struct StaticCommands {
     reward_gold: Option<RewardGoldCommand>,
+    reward_experience: Option<RewardExperienceCommand>,
+    pub(crate) reward_item: Option<RewardItemCommand>,
+    pub(crate) reward_loot: Option<LootTableId>,
+    pub(crate) remove_item: Option<RemoveItemCommand>,
+    #[schemars(with = "Option<std::collections::HashMap<NpcId, CharacterUpdate>>")]
     update_characters: Option<HashMap<NpcId, CharacterUpdate>>,
-    scene_entry: Option<HashMap<SceneId, SceneSectionId>>,
+    #[schemars(with = "Option<std::collections::HashMap<SceneId, SceneSectionId>>")]
+    pub(crate) scene_entry: Option<HashMap<SceneId, SceneSectionId>>,
     #[serde(alias = "vars")]
+    #[schemars(with = "Option<std::collections::HashMap<String, String>>")]
     variables: Option<HashMap<String, String>>,
     battle: Option<NpcId>,
+    play_sound: Option<PlaySoundCommand>,
     kill_character: Option<TODO>,
-    set_quest_stage: Option<TODO>,
-    complete_quest: Option<TODO>,
+    pub(crate) set_quest_stage: Option<TODO>,
+    pub(crate) complete_quest: Option<TODO>,
 }
 
 impl StaticCommands {
-    pub fn execute(
-        self,
-        scene_manager: &mut SceneManager,
-        start_battle_event: &mut EventWriter<StartBattleEvent>,
-        update_npc_event: &mut EventWriter<UpdateNpcEvent>,
-    ) {
+    pub fn execute(self, scene_manager: &mut SceneManager, writers: &mut StaticCommandsWriters) {
         // TODO: reward_gold
+        if let Some(reward_experience) = self.reward_experience {
+            writers
+                .reward_experience
+                .write(RewardExperienceEvent(reward_experience.amount));
+        }
+        if let Some(reward_item) = self.reward_item {
+            writers.reward_item.write(RewardItemEvent {
+                item_id: reward_item.item().to_owned(),
+                count: reward_item.count(),
+            });
+        }
+        if let Some(reward_loot) = self.reward_loot {
+            writers.reward_loot.write(RewardLootEvent(reward_loot));
+        }
+        if let Some(remove_item) = self.remove_item {
+            writers.remove_item.write(RemoveItemEvent {
+                item_id: remove_item.item,
+                count: remove_item.count.unwrap_or(1),
+                recipient: remove_item.recipient,
+            });
+        }
         if let Some(update_characters) = self.update_characters {
             for (npc_id, character_update) in update_characters {
-                update_npc_event.write(UpdateNpcEvent(npc_id, character_update));
+                writers
+                    .update_npc
+                    .write(UpdateNpcEvent(npc_id, character_update));
             }
         }
         if let Some(scene_entry) = self.scene_entry {
@@ -85,8 +275,14 @@ impl StaticCommands {
             info!("updating variables: {variables:?}");
             scene_manager.update_variables(variables);
         }
+        if let Some(play_sound) = self.play_sound {
+            writers.play_sound.write(PlaySoundEvent {
+                sound: play_sound.sound().to_string(),
+                volume: play_sound.volume(),
+            });
+        }
         if let Some(battle) = self.battle {
-            start_battle_event.write(StartBattleEvent(battle));
+            writers.start_battle.write(StartBattleEvent(battle));
         }
         // TODO: kill_character
         // TODO: set_quest_stage
@@ -2,6 +2,7 @@ use bevy::prelude::*;
 
 use crate::components::*;
 use crate::events::*;
+use crate::{QUICK_SAVE_SLOT, ShowBacklog};
 
 pub fn debug_attack(
     player_query: Query<Entity, With<Player>>,
@@ -32,8 +33,25 @@ pub fn debug_show_all_entities(
     }
 }
 
-pub fn exit_on_esc(keyboard_input: Res<ButtonInput<KeyCode>>, mut exit: EventWriter<AppExit>) {
-    if keyboard_input.just_pressed(KeyCode::Escape) {
+/// Quits on Escape, unless the backlog is open, where Escape closes it.
+pub fn exit_on_esc(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    show_backlog: Res<ShowBacklog>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) && !show_backlog.0 {
         exit.write(AppExit::Success);
     }
 }
+
+/// Saves to the quick save slot on F5.
+pub fn quick_save(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut save_game_event: EventWriter<SaveGameEvent>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F5) {
+        save_game_event.write(SaveGameEvent {
+            slot: QUICK_SAVE_SLOT.to_string(),
+        });
+    }
+}
@@ -0,0 +1,415 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_ratatui::{RatatuiContext, event::KeyEvent};
+use crossterm::event::{KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    style::Stylize,
+    text::{Line as TextLine, Text},
+    widgets::{Block, Paragraph, Wrap},
+};
+
+use crate::{
+    AttackEvent, Battle, DEBUG, DebugPlaySceneId, DialogueHistory, EndBattleEvent, EndSceneEvent,
+    FormattedLine, HistoryEntry, LoadDiagnostics, Npc, PlaySceneEvent, Player, QUICK_SAVE_SLOT,
+    RpgEntity, SaveGameEvent, SceneManager, ScenePlayer, ScenePlayerInput, ShowBacklog, Skill,
+    StaticCommandsEvent, Toasts, Typewriter, UiScenePart,
+};
+
+/// How often the terminal is redrawn.
+pub const TERMINAL_FRAME_TIME: Duration = Duration::from_millis(1000 / 30);
+/// Backlog entries shown above the dialogue.
+const BACKLOG_LINES: usize = 8;
+
+/// The skill highlighted in the level up panel.
+#[derive(Resource, Debug, Default)]
+pub struct LevelUpCursor(usize);
+
+/// Keys pressed this frame. Terminals that report releases and repeats have
+/// those left out.
+fn pressed(key_events: &mut EventReader<KeyEvent>) -> impl Iterator<Item = KeyEvent> {
+    key_events
+        .read()
+        .filter(|event| event.kind == KeyEventKind::Press)
+        .cloned()
+}
+
+pub fn terminal_exit(mut key_events: EventReader<KeyEvent>, mut exit: EventWriter<AppExit>) {
+    for event in pressed(&mut key_events) {
+        let ctrl_c =
+            event.code == KeyCode::Char('c') && event.modifiers.contains(KeyModifiers::CONTROL);
+        if ctrl_c || event.code == KeyCode::Esc {
+            exit.write(AppExit::Success);
+        }
+    }
+}
+
+/// Saves to the quick save slot on F5.
+pub fn terminal_quick_save(
+    mut key_events: EventReader<KeyEvent>,
+    mut save_game_event: EventWriter<SaveGameEvent>,
+) {
+    for event in pressed(&mut key_events) {
+        if event.code == KeyCode::F(5) {
+            save_game_event.write(SaveGameEvent {
+                slot: QUICK_SAVE_SLOT.to_string(),
+            });
+        }
+    }
+}
+
+pub fn terminal_dialogue_input(
+    mut key_events: EventReader<KeyEvent>,
+    mut scene_manager: ResMut<SceneManager>,
+    mut scene_player: Option<ResMut<ScenePlayer>>,
+    mut end_scene_event: EventWriter<EndSceneEvent>,
+    mut static_command_event: EventWriter<StaticCommandsEvent>,
+    mut typewriter: ResMut<Typewriter>,
+    mut history: ResMut<DialogueHistory>,
+    mut show_backlog: ResMut<ShowBacklog>,
+    player_query: Query<&RpgEntity, With<Player>>,
+) {
+    let (Some(scene_player), Ok(player)) = (scene_player.as_deref_mut(), player_query.single())
+    else {
+        return;
+    };
+
+    for event in pressed(&mut key_events) {
+        let input = match event.code {
+            KeyCode::Char('b') => {
+                show_backlog.0 = !show_backlog.0;
+                continue;
+            }
+            KeyCode::Up | KeyCode::Char('w') | KeyCode::Char('k') => ScenePlayerInput::MoveUp,
+            KeyCode::Down | KeyCode::Char('s') | KeyCode::Char('j') => ScenePlayerInput::MoveDown,
+            KeyCode::Enter | KeyCode::Char('e') | KeyCode::Char(' ') => {
+                ScenePlayerInput::SelectCurrent
+            }
+            // responses are numbered from 1 on screen, counting only the
+            // ones shown
+            KeyCode::Char(c @ '1'..='9') => {
+                let available = scene_player.available_responses(&scene_manager, &player.inventory);
+                match available.get(c as usize - '1' as usize) {
+                    Some(&i) => ScenePlayerInput::Select(i),
+                    None => continue,
+                }
+            }
+            _ => continue,
+        };
+        let selects = matches!(
+            input,
+            ScenePlayerInput::SelectCurrent | ScenePlayerInput::Select(_)
+        );
+        // the first press finishes typing out the line, the second advances
+        if selects && !typewriter.is_complete(&scene_player.line_bookmark()) {
+            typewriter.complete();
+            continue;
+        }
+        let scene = scene_player.scene().clone();
+        if let Some(response) = scene_player.input(
+            input,
+            &mut scene_manager,
+            &mut end_scene_event,
+            &mut static_command_event,
+        ) {
+            let response = FormattedLine::new(&response, &scene_manager, player.name());
+            history.record_response(&scene, &response.text);
+        }
+    }
+}
+
+pub fn terminal_dialogue_ui(
+    mut context: ResMut<RatatuiContext>,
+    scene_player: Option<Res<ScenePlayer>>,
+    scene_manager: Res<SceneManager>,
+    mut scene_commands_event: EventWriter<StaticCommandsEvent>,
+    player_query: Query<&RpgEntity, With<Player>>,
+    npc_query: Query<(&Npc, &RpgEntity)>,
+    typewriter: Res<Typewriter>,
+    history: Res<DialogueHistory>,
+    show_backlog: Res<ShowBacklog>,
+    toasts: Res<Toasts>,
+) {
+    let (Some(scene_player), Ok(player)) = (scene_player, player_query.single()) else {
+        return;
+    };
+    let Some(UiScenePart { line, responses }) =
+        scene_player.get_current(&scene_manager, &player.inventory, &mut scene_commands_event)
+    else {
+        // dialogue without lines is skipped by `ScenePlayer::update`
+        return;
+    };
+
+    let speaker_name = npc_query
+        .iter()
+        .find(|(npc, _rpg_entity)| npc.id == line.from)
+        .map(|(_npc, rpg_entity)| rpg_entity.name())
+        .unwrap_or(&line.from.0);
+    let bookmark = scene_player.line_bookmark();
+    let text = FormattedLine::new(&line.text, &scene_manager, player.name()).text;
+    let available = scene_player.available_responses(&scene_manager, &player.inventory);
+    let response_lines: Vec<TextLine> = match responses {
+        Some(responses) if typewriter.is_complete(&bookmark) && !responses.is_empty() => responses
+            .iter()
+            .enumerate()
+            .map(|(i, response)| {
+                let response = FormattedLine::new(&response.text, &scene_manager, player.name());
+                let text = format!("{}. {}", i + 1, response.text);
+                if available.get(i) == Some(&scene_player.highlighted_response()) {
+                    TextLine::from(format!("> {text}")).yellow().bold()
+                } else {
+                    TextLine::from(format!("  {text}"))
+                }
+            })
+            .collect(),
+        _ => vec![TextLine::from("> <continue>").yellow().bold()],
+    };
+
+    let backlog: Vec<TextLine> = history
+        .entries()
+        .iter()
+        .rev()
+        .take(BACKLOG_LINES)
+        .rev()
+        .map(|entry| match entry {
+            HistoryEntry::Line { speaker, text, .. } => {
+                TextLine::from(format!("{speaker}: {text}"))
+            }
+            HistoryEntry::Response { text, .. } => TextLine::from(format!("> {text}")).yellow(),
+        })
+        .collect();
+
+    let draw = context.draw(|frame| {
+        let [backlog_area, line_area, responses_area, hints_area] = Layout::vertical([
+            Constraint::Length(if show_backlog.0 {
+                BACKLOG_LINES as u16 + 2
+            } else {
+                0
+            }),
+            Constraint::Min(5),
+            Constraint::Length(response_lines.len() as u16 + 2),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        if show_backlog.0 {
+            frame.render_widget(
+                Paragraph::new(backlog)
+                    .wrap(Wrap { trim: false })
+                    .block(Block::bordered().title("Backlog")),
+                backlog_area,
+            );
+        }
+        frame.render_widget(
+            Paragraph::new(typewriter.visible(&bookmark, &text))
+                .wrap(Wrap { trim: false })
+                .block(Block::bordered().title(speaker_name.bold())),
+            line_area,
+        );
+        frame.render_widget(
+            Paragraph::new(response_lines).block(Block::bordered()),
+            responses_area,
+        );
+        frame.render_widget(
+            TextLine::from("↑/↓ choose  enter select  1-9 pick  b backlog  esc quit").dark_gray(),
+            hints_area,
+        );
+        render_toasts(frame, &toasts);
+    });
+    if let Err(e) = draw {
+        error!("failed to draw terminal: {e}");
+    }
+}
+
+pub fn terminal_battle_input(
+    mut key_events: EventReader<KeyEvent>,
+    player_query: Query<Entity, With<Player>>,
+    npc_query: Query<&RpgEntity, With<Npc>>,
+    battle: Res<Battle>,
+    mut attack_event: EventWriter<AttackEvent>,
+    mut end_battle_event: EventWriter<EndBattleEvent>,
+) {
+    let (Ok(player), Ok(opponent)) = (player_query.single(), npc_query.get(battle.0)) else {
+        return;
+    };
+    for event in pressed(&mut key_events) {
+        match event.code {
+            KeyCode::Char('a') | KeyCode::Enter if opponent.is_alive() => {
+                // TODO: proper battle gameplay
+                attack_event.write(AttackEvent {
+                    attacker: player,
+                    victim: battle.0,
+                });
+            }
+            KeyCode::Enter => {
+                end_battle_event.write(EndBattleEvent);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn terminal_battle_ui(
+    mut context: ResMut<RatatuiContext>,
+    player_query: Query<&RpgEntity, With<Player>>,
+    npc_query: Query<&RpgEntity, With<Npc>>,
+    battle: Res<Battle>,
+    toasts: Res<Toasts>,
+) {
+    let (Ok(player), Ok(opponent)) = (player_query.single(), npc_query.get(battle.0)) else {
+        return;
+    };
+    let mut lines = vec![
+        health_line(player),
+        health_line(opponent),
+        TextLine::default(),
+    ];
+    if opponent.is_alive() {
+        lines.push(TextLine::from("a / enter: attack").yellow());
+    } else {
+        lines.push(TextLine::from(format!("{} is dead.", opponent.name())));
+        lines.push(TextLine::from("enter: continue").yellow());
+    }
+
+    let draw = context.draw(|frame| {
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Battle".bold())),
+            frame.area(),
+        );
+        render_toasts(frame, &toasts);
+    });
+    if let Err(e) = draw {
+        error!("failed to draw terminal: {e}");
+    }
+}
+
+pub fn terminal_map_input(
+    mut key_events: EventReader<KeyEvent>,
+    mut play_scene_event: EventWriter<PlaySceneEvent>,
+    mut debug_new_scene_id: ResMut<DebugPlaySceneId>,
+    mut player_query: Query<&mut RpgEntity, With<Player>>,
+    mut level_up_cursor: ResMut<LevelUpCursor>,
+) {
+    let Ok(mut player) = player_query.single_mut() else {
+        return;
+    };
+    for event in pressed(&mut key_events) {
+        let leveling_up = player.unspent_points() > 0;
+        match event.code {
+            KeyCode::Up if leveling_up => {
+                level_up_cursor.0 = level_up_cursor.0.saturating_sub(1);
+            }
+            KeyCode::Down if leveling_up => {
+                level_up_cursor.0 = (level_up_cursor.0 + 1).min(Skill::ALL.len() - 1);
+            }
+            KeyCode::Char('+') if leveling_up => {
+                player.allocate_point(Skill::ALL[level_up_cursor.0]);
+            }
+            KeyCode::Char(c) if !event.modifiers.contains(KeyModifiers::CONTROL) => {
+                debug_new_scene_id.0.push(c)
+            }
+            KeyCode::Backspace => {
+                debug_new_scene_id.0.pop();
+            }
+            KeyCode::Enter if !debug_new_scene_id.0.is_empty() => {
+                play_scene_event.write(PlaySceneEvent(debug_new_scene_id.0.clone().into()));
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn terminal_map_ui(
+    mut context: ResMut<RatatuiContext>,
+    player_query: Query<&RpgEntity, With<Player>>,
+    debug_new_scene_id: Res<DebugPlaySceneId>,
+    level_up_cursor: Res<LevelUpCursor>,
+    load_diagnostics: Res<LoadDiagnostics>,
+    toasts: Res<Toasts>,
+) {
+    let Ok(player) = player_query.single() else {
+        return;
+    };
+
+    let mut lines = vec![
+        TextLine::from(format!(
+            "{} - level {} ({} xp)",
+            player.name(),
+            player.level(),
+            player.experience()
+        )),
+        health_line(player),
+        TextLine::default(),
+        TextLine::from(format!("play scene: {}_", debug_new_scene_id.0)),
+    ];
+    if player.unspent_points() > 0 {
+        lines.push(TextLine::default());
+        lines.push(
+            TextLine::from(format!(
+                "Level up! points to spend: {} (↑/↓ choose, + spend)",
+                player.unspent_points()
+            ))
+            .yellow(),
+        );
+        for (i, skill) in Skill::ALL.into_iter().enumerate() {
+            let text = format!("{skill}: {}", player.stats().get(skill));
+            lines.push(if i == level_up_cursor.0 {
+                TextLine::from(format!("> {text}")).bold()
+            } else {
+                TextLine::from(format!("  {text}"))
+            });
+        }
+    }
+    if DEBUG && !load_diagnostics.0.is_empty() {
+        lines.push(TextLine::default());
+        lines.push(TextLine::from(format!("Load Errors ({})", load_diagnostics.0.len())).red());
+        for diagnostic in &load_diagnostics.0 {
+            lines.push(TextLine::from(diagnostic.to_string()).red());
+        }
+    }
+
+    let draw = context.draw(|frame| {
+        frame.render_widget(
+            Paragraph::new(lines)
+                .wrap(Wrap { trim: false })
+                .block(Block::bordered().title("Map Mode".bold())),
+            frame.area(),
+        );
+        render_toasts(frame, &toasts);
+    });
+    if let Err(e) = draw {
+        error!("failed to draw terminal: {e}");
+    }
+}
+
+fn health_line(rpg_entity: &RpgEntity) -> TextLine<'static> {
+    TextLine::from(format!(
+        "{} HP: {}/{}",
+        rpg_entity.name(),
+        rpg_entity.health(),
+        rpg_entity.max_health()
+    ))
+}
+
+/// Draws toasts over the bottom of whatever else is on screen.
+fn render_toasts(frame: &mut Frame, toasts: &Toasts) {
+    let messages: Vec<TextLine> = toasts
+        .messages()
+        .map(|message| TextLine::from(message.to_string()).red())
+        .collect();
+    if messages.is_empty() {
+        return;
+    }
+    let area = frame.area();
+    let height = (messages.len() as u16 + 2).min(area.height);
+    let area = Rect::new(area.x, area.bottom() - height, area.width, height);
+    frame.render_widget(ratatui::widgets::Clear, area);
+    frame.render_widget(
+        Paragraph::new(Text::from(messages))
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered()),
+        area,
+    );
+}
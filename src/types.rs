@@ -1,11 +1,14 @@
 use std::ops;
 
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 #[allow(clippy::upper_case_acronyms)]
 pub type TODO = serde_json::Value;
 
-#[derive(Deserialize, Debug, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(
+    Serialize, Deserialize, JsonSchema, Debug, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
 pub struct Position {
     x: u32,
     y: u32,
@@ -17,6 +20,18 @@ impl Position {
     }
 }
 
+/// Parses `x,y`, as written on the command line.
+impl std::str::FromStr for Position {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((x, y)) = s.split_once(',') else {
+            anyhow::bail!("expected a position like `3,4`, got {s:?}");
+        };
+        Ok(Self::new(x.trim().parse()?, y.trim().parse()?))
+    }
+}
+
 impl ops::Add<Position> for Position {
     type Output = Position;
 
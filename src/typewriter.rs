@@ -0,0 +1,384 @@
+use bevy::{platform::collections::HashSet, prelude::*};
+
+use crate::{
+    EndSceneEvent, Npc, NpcVoice, Player, RpgEntity, SceneBookmark, SceneManager, ScenePlayer,
+    ScenePlayerInput, SpeechBlipEvent, StaticCommandsEvent, VoiceId,
+};
+
+/// Markup that holds the typewriter, and the fraction of
+/// [`TypewriterSettings::markup_pause`] it waits.
+const PAUSE_TAGS: &[(&str, f32)] = &[
+    ("<pause>", 1.0),
+    ("<hpause>", 1.0 / 2.0),
+    ("<tpause>", 1.0 / 3.0),
+    ("<qpause>", 1.0 / 4.0),
+];
+/// The variable `%$NAME%` falls back to when a scene hasn't set it.
+const PLAYER_NAME_VARIABLE: &str = "NAME";
+/// How many variables set to `$other` are followed before giving up, so
+/// variables naming each other can't loop forever.
+const MAX_VARIABLE_HOPS: usize = 8;
+
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct TypewriterSettings {
+    pub characters_per_second: f32,
+    /// Extra delay after `.`, `!` and `?`.
+    pub sentence_pause: f32,
+    /// Extra delay after `,`, `;` and `:`.
+    pub clause_pause: f32,
+    /// Delay for `<pause>` in a line. `<hpause>`, `<tpause>` and `<qpause>`
+    /// wait a half, third and quarter of it.
+    pub markup_pause: f32,
+    /// Advance on its own once a line has been shown for `auto_advance_delay`.
+    pub auto_advance: bool,
+    pub auto_advance_delay: f32,
+    /// Skip straight past lines that have been seen before.
+    pub fast_forward: bool,
+}
+
+impl Default for TypewriterSettings {
+    fn default() -> Self {
+        Self {
+            characters_per_second: 30.0,
+            sentence_pause: 0.3,
+            clause_pause: 0.12,
+            markup_pause: 0.6,
+            auto_advance: false,
+            auto_advance_delay: 1.5,
+            fast_forward: false,
+        }
+    }
+}
+
+impl TypewriterSettings {
+    fn pause_after(&self, character: char) -> f32 {
+        match character {
+            '.' | '!' | '?' => self.sentence_pause,
+            ',' | ';' | ':' => self.clause_pause,
+            _ => 0.0,
+        }
+    }
+}
+
+/// A line's text as the player sees it, with `%$variable%` filled in and the
+/// pause markup taken out. The dialogue boxes and the
+/// history both show this, so they agree on what was said.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FormattedLine {
+    pub text: String,
+    /// Each pause, after how many characters of `text` it comes and what
+    /// fraction of [`TypewriterSettings::markup_pause`] it waits.
+    pauses: Vec<(usize, f32)>,
+}
+
+impl FormattedLine {
+    pub fn new(text: &str, scene_manager: &SceneManager, player_name: &str) -> Self {
+        let mut line = Self::default();
+        let mut characters = 0;
+        let mut rest = text;
+        while let Some(start) = rest.find(['<', '%']) {
+            line.text.push_str(&rest[..start]);
+            characters += rest[..start].chars().count();
+            rest = &rest[start..];
+            if let Some(&(tag, fraction)) = PAUSE_TAGS.iter().find(|(tag, _)| rest.starts_with(tag))
+            {
+                line.pauses.push((characters, fraction));
+                rest = &rest[tag.len()..];
+            } else if let Some((variable, after)) = rest
+                .strip_prefix("%$")
+                .and_then(|rest| rest.split_once('%'))
+            {
+                let value = resolve_variable(variable, scene_manager, player_name);
+                characters += value.chars().count();
+                line.text.push_str(&value);
+                rest = after;
+            } else {
+                // a `<` or `%` that isn't markup
+                line.text.push_str(&rest[..1]);
+                characters += 1;
+                rest = &rest[1..];
+            }
+        }
+        line.text.push_str(rest);
+        line
+    }
+}
+
+/// The value of `variable`. A variable set to `$other` takes the value of
+/// `other`, and `NAME` is the player's name unless a scene set it. Unknown
+/// variables are left as written, so they stand out.
+fn resolve_variable(variable: &str, scene_manager: &SceneManager, player_name: &str) -> String {
+    let mut variable = variable;
+    for _ in 0..MAX_VARIABLE_HOPS {
+        match scene_manager.get_variable(variable) {
+            Some(value) => match value.strip_prefix('$') {
+                Some(other) => variable = other,
+                None => return value.clone(),
+            },
+            None if variable == PLAYER_NAME_VARIABLE => return player_name.to_string(),
+            None => break,
+        }
+    }
+    format!("%${variable}%")
+}
+
+/// Reveals the current line one character at a time.
+#[derive(Resource, Debug, Default)]
+pub struct Typewriter {
+    bookmark: Option<SceneBookmark>,
+    revealed: usize,
+    complete: bool,
+    /// Time left until the next character is revealed.
+    delay: f32,
+    /// How many of the line's pauses have been waited out.
+    paused: usize,
+    /// Time the completed line has been on screen.
+    shown_for: f32,
+    was_seen: bool,
+    seen: HashSet<SceneBookmark>,
+}
+
+impl Typewriter {
+    fn is_current(&self, bookmark: &SceneBookmark) -> bool {
+        self.bookmark.as_ref() == Some(bookmark)
+    }
+
+    /// Starts revealing the line at `bookmark`, remembering it as seen.
+    pub fn start(&mut self, bookmark: SceneBookmark) {
+        self.was_seen = !self.seen.insert(bookmark.clone());
+        self.bookmark = Some(bookmark);
+        self.revealed = 0;
+        self.complete = false;
+        self.delay = 0.0;
+        self.paused = 0;
+        self.shown_for = 0.0;
+    }
+
+    /// Forgets the current line, so replaying a scene types it out again.
+    pub fn reset(&mut self) {
+        self.bookmark = None;
+    }
+
+    pub fn has_seen(&self, bookmark: &SceneBookmark) -> bool {
+        self.seen.contains(bookmark)
+    }
+
+    pub fn is_complete(&self, bookmark: &SceneBookmark) -> bool {
+        self.is_current(bookmark) && self.complete
+    }
+
+    pub fn complete(&mut self) {
+        self.complete = true;
+    }
+
+    /// The part of `text` revealed so far for the line at `bookmark`.
+    pub fn visible<'a>(&self, bookmark: &SceneBookmark, text: &'a str) -> &'a str {
+        if !self.is_current(bookmark) {
+            return "";
+        }
+        if self.complete {
+            return text;
+        }
+        text.char_indices()
+            .nth(self.revealed)
+            .map(|(i, _)| &text[..i])
+            .unwrap_or(text)
+    }
+
+    /// Advances the reveal by `delta` seconds and returns the newly revealed
+    /// characters. Pauses in the line hold the reveal without showing anything.
+    pub fn tick(
+        &mut self,
+        line: &FormattedLine,
+        delta: f32,
+        settings: &TypewriterSettings,
+    ) -> Vec<char> {
+        if self.complete {
+            self.shown_for += delta;
+            return Vec::new();
+        }
+
+        let text = &line.text;
+        let mut revealed = Vec::new();
+        self.delay -= delta;
+        while self.delay <= 0.0 {
+            if let Some(&(at, fraction)) = line.pauses.get(self.paused)
+                && at <= self.revealed
+            {
+                self.paused += 1;
+                self.delay += settings.markup_pause * fraction;
+                continue;
+            }
+            let Some(character) = text.chars().nth(self.revealed) else {
+                self.complete = true;
+                break;
+            };
+            self.revealed += 1;
+            self.delay +=
+                1.0 / settings.characters_per_second.max(1.0) + settings.pause_after(character);
+            revealed.push(character);
+        }
+        if text.chars().nth(self.revealed).is_none() {
+            self.complete = true;
+        }
+        revealed
+    }
+}
+
+pub fn update_typewriter(
+    time: Res<Time>,
+    settings: Res<TypewriterSettings>,
+    mut typewriter: ResMut<Typewriter>,
+    scene_player: Option<ResMut<ScenePlayer>>,
+    mut scene_manager: ResMut<SceneManager>,
+    npc_query: Query<&Npc>,
+    player_query: Query<&RpgEntity, With<Player>>,
+    mut speech_blip_events: EventWriter<SpeechBlipEvent>,
+    mut end_scene_event: EventWriter<EndSceneEvent>,
+    mut scene_commands_event: EventWriter<StaticCommandsEvent>,
+) {
+    let Some(mut scene_player) = scene_player else {
+        return;
+    };
+    if scene_player.is_added() {
+        typewriter.reset();
+    }
+    let Some(line) = scene_player.current_line(&scene_manager) else {
+        return;
+    };
+
+    let bookmark = scene_player.line_bookmark();
+    if !typewriter.is_current(&bookmark) {
+        typewriter.start(bookmark);
+    }
+
+    let awaiting_response = scene_player.awaiting_response(&scene_manager);
+    if settings.fast_forward && typewriter.was_seen {
+        typewriter.complete();
+        if !awaiting_response {
+            scene_player.input(
+                ScenePlayerInput::SelectCurrent,
+                &mut scene_manager,
+                &mut end_scene_event,
+                &mut scene_commands_event,
+            );
+        }
+        return;
+    }
+
+    let voice = npc_query
+        .iter()
+        .find(|npc| npc.id == line.from)
+        .map(|npc| VoiceId::from(&npc.voice))
+        .unwrap_or_else(|| VoiceId::from(&NpcVoice::default()));
+    let player_name = player_query
+        .single()
+        .map(RpgEntity::name)
+        .unwrap_or_default();
+    let line = FormattedLine::new(&line.text, &scene_manager, player_name);
+    for character in typewriter.tick(&line, time.delta_secs(), &settings) {
+        if character.is_alphanumeric() {
+            speech_blip_events.write(SpeechBlipEvent(voice.clone()));
+        }
+    }
+
+    if settings.auto_advance
+        && !awaiting_response
+        && typewriter.complete
+        && typewriter.shown_for >= settings.auto_advance_delay
+    {
+        scene_player.input(
+            ScenePlayerInput::SelectCurrent,
+            &mut scene_manager,
+            &mut end_scene_event,
+            &mut scene_commands_event,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        FormattedLine, SceneBookmark, SceneId, SceneManager, Typewriter, TypewriterSettings,
+    };
+
+    fn bookmark(line: usize) -> SceneBookmark {
+        SceneBookmark::new(&SceneId::new("test"), None, Some(line), None)
+    }
+
+    #[test]
+    fn reveals_at_configured_speed_with_punctuation_pauses() {
+        let settings = TypewriterSettings {
+            characters_per_second: 4.0,
+            sentence_pause: 1.0,
+            ..Default::default()
+        };
+        let text = "Hi. Bye";
+        let line = FormattedLine::new(text, &SceneManager::new(), "Jake");
+        let mut typewriter = Typewriter::default();
+        typewriter.start(bookmark(0));
+
+        assert_eq!(typewriter.tick(&line, 0.0, &settings), vec!['H']);
+        assert_eq!(typewriter.tick(&line, 0.25, &settings), vec!['i']);
+        assert_eq!(typewriter.tick(&line, 0.25, &settings), vec!['.']);
+        // waiting out the pause after the full stop
+        assert!(typewriter.tick(&line, 0.5, &settings).is_empty());
+        assert_eq!(typewriter.visible(&bookmark(0), text), "Hi.");
+        assert_eq!(typewriter.tick(&line, 0.75, &settings), vec![' ']);
+        assert!(!typewriter.is_complete(&bookmark(0)));
+
+        typewriter.tick(&line, 10.0, &settings);
+        assert!(typewriter.is_complete(&bookmark(0)));
+        assert_eq!(typewriter.visible(&bookmark(0), text), text);
+        assert_eq!(typewriter.visible(&bookmark(1), text), "");
+    }
+
+    #[test]
+    fn pause_markup_holds_the_reveal_without_showing() {
+        let settings = TypewriterSettings {
+            characters_per_second: 4.0,
+            markup_pause: 1.0,
+            ..Default::default()
+        };
+        let text = "Hi<pause> you";
+        let line = FormattedLine::new(text, &SceneManager::new(), "Jake");
+        let mut typewriter = Typewriter::default();
+        typewriter.start(bookmark(0));
+
+        assert_eq!(typewriter.tick(&line, 0.0, &settings), vec!['H']);
+        assert_eq!(typewriter.tick(&line, 0.25, &settings), vec!['i']);
+        assert!(typewriter.tick(&line, 0.25, &settings).is_empty());
+        assert!(typewriter.tick(&line, 0.5, &settings).is_empty());
+        assert_eq!(typewriter.tick(&line, 0.5, &settings), vec![' ']);
+        typewriter.tick(&line, 10.0, &settings);
+        assert_eq!(typewriter.visible(&bookmark(0), &line.text), "Hi you");
+    }
+
+    #[test]
+    fn lines_are_formatted_with_variables() {
+        let mut scene_manager = SceneManager::new();
+        scene_manager.update_variables([
+            ("jakes-nick".to_string(), "$NAME".to_string()),
+            ("mood".to_string(), "glad".to_string()),
+        ]);
+        let line = FormattedLine::new(
+            "Bye, %$jakes-nick%!<hpause> I'm 100% %$mood%, <3 %$missing%",
+            &scene_manager,
+            "Jake",
+        );
+        assert_eq!(line.text, "Bye, Jake! I'm 100% glad, <3 %$missing%");
+        assert_eq!(line.pauses, [(10, 0.5)]);
+    }
+
+    #[test]
+    fn tracks_seen_lines() {
+        let mut typewriter = Typewriter::default();
+        typewriter.start(bookmark(0));
+        assert!(!typewriter.was_seen);
+        typewriter.start(bookmark(1));
+        typewriter.start(bookmark(0));
+        assert!(typewriter.was_seen);
+        assert!(typewriter.has_seen(&bookmark(1)));
+        assert!(!typewriter.has_seen(&bookmark(2)));
+    }
+}
@@ -2,18 +2,22 @@ use bevy::prelude::*;
 use bevy_egui::{
     EguiContexts,
     egui::{
-        self, Align2, CollapsingHeader, Color32, Frame, Label, Margin, RichText, ScrollArea,
+        self, Align2, CollapsingHeader, Color32, Frame, Margin, RichText, ScrollArea,
         SelectableLabel, Stroke, TextEdit, TextStyle, Ui, Widget, Window, load::SizedTexture,
     },
 };
 
 use crate::{
-    AttackEvent, Battle, DebugPlaySceneId, EndBattleEvent, EndSceneEvent, ItemManager, Npc,
-    PixelBufferImageId, PlaySceneEvent, Player, RpgEntity, SceneManager, ScenePlayer,
-    ScenePlayerInput, StaticCommandsEvent, UiScenePart,
+    AttackEvent, Battle, CurrentMap, DebugPlaySceneId, DialogueHistory, EndBattleEvent,
+    EndSceneEvent, FormattedLine, HistoryEntry, ItemManager, LoadDiagnostics, Npc,
+    PixelBufferImageId, PlaySceneEvent, Player, PlayerPosition, Portraits, RpgEntity, SceneManager,
+    ScenePlayer, ScenePlayerInput, ShowBacklog, Skill, StaticCommandsEvent, Typewriter,
+    TypewriterSettings, UiScenePart,
 };
 
 const SCALE_FACTOR: f32 = 16.0;
+/// Seconds a toast stays on screen.
+const TOAST_DURATION: f32 = 6.0;
 
 pub fn dialogue_ui(
     mut contexts: EguiContexts,
@@ -21,32 +25,42 @@ pub fn dialogue_ui(
     mut scene_manager: ResMut<SceneManager>,
     mut scene_commands_event: EventWriter<StaticCommandsEvent>,
     mut end_scene_event: EventWriter<EndSceneEvent>,
+    player_query: Query<&RpgEntity, With<Player>>,
     npc_query: Query<(&Npc, &RpgEntity)>,
+    portraits: Res<Portraits>,
+    asset_server: Res<AssetServer>,
+    mut typewriter: ResMut<Typewriter>,
+    mut typewriter_settings: ResMut<TypewriterSettings>,
+    mut history: ResMut<DialogueHistory>,
+    mut show_backlog: ResMut<ShowBacklog>,
 ) {
     let ctx = contexts.ctx_mut();
+    let player = player_query.single().expect("player must exist.");
 
     let mut scene_player_input = None;
     let Some(UiScenePart { line, responses }) =
-        scene_player.get_current(&scene_manager, &mut scene_commands_event)
+        scene_player.get_current(&scene_manager, &player.inventory, &mut scene_commands_event)
     else {
-        scene_player.input(
-            ScenePlayerInput::SelectCurrent,
-            &mut scene_manager,
-            &mut end_scene_event,
-            &mut scene_commands_event,
-        );
+        // dialogue without lines is skipped by `ScenePlayer::update`
         return;
     };
 
     let fallback_name = &line.from.0;
-    let speaker_rpg_entity = if let Some((_npc, rpg_entity)) = npc_query
+    let speaker = npc_query
         .iter()
-        .find(|(npc, _rpg_entity)| npc.id == line.from)
-    {
-        Some(rpg_entity)
-    } else {
-        None
-    };
+        .find(|(npc, _rpg_entity)| npc.id == line.from);
+    let speaker_rpg_entity = speaker.map(|(_npc, rpg_entity)| rpg_entity);
+    let speaker_portrait =
+        speaker.and_then(|(npc, _rpg_entity)| portraits.get(&npc.image.0, &asset_server));
+    let speaker_name = speaker_rpg_entity
+        .map(|e| e.name())
+        .unwrap_or(fallback_name);
+    let bookmark = scene_player.line_bookmark();
+    let text = FormattedLine::new(&line.text, &scene_manager, player.name()).text;
+    let line_complete = typewriter.is_complete(&bookmark);
+    // `responses` leaves out the ones whose conditions aren't met, while
+    // inputs index every response
+    let available = scene_player.available_responses(&scene_manager, &player.inventory);
 
     Window::new("Dialogue")
         .collapsible(false)
@@ -67,36 +81,41 @@ pub fn dialogue_ui(
                     // left side: name and dialogue
                     ui.vertical(|ui| {
                         ui.label(
-                            RichText::new(
-                                speaker_rpg_entity
-                                    .map(|e| e.name())
-                                    .unwrap_or(fallback_name),
-                            )
-                            .text_style(TextStyle::Heading)
-                            .color(Color32::WHITE),
+                            RichText::new(speaker_name)
+                                .text_style(TextStyle::Heading)
+                                .color(Color32::WHITE),
                         );
 
                         Frame::dark_canvas(ui.style()).show(ui, |ui| {
-                            // TODO: apply text formatting
-                            ui.label(&line.text);
+                            ui.label(typewriter.visible(&bookmark, &text));
                         })
                     });
 
-                    if let Some(_speaker_rpg_entity) = speaker_rpg_entity {
+                    if let Some(speaker_portrait) = speaker_portrait {
                         // right: speaker image
-                        let image_size = egui::vec2(100.0, 60.0);
-                        // TODO: get character image from character query
-                        ui.add_sized(image_size, Label::new("<image here>"));
+                        let image_size = egui::vec2(100.0, 100.0);
+                        ui.image(SizedTexture::new(speaker_portrait, image_size));
                     }
                 });
 
                 // response row
                 Frame::dark_canvas(ui.style()).show(ui, |ui| {
                     ScrollArea::vertical().show(ui, |ui| match responses {
+                        _ if !line_complete => {
+                            if response_button(ui, "<continue>", true).clicked() {
+                                typewriter.complete();
+                            };
+                        }
                         Some(responses) if !responses.is_empty() => {
-                            responses.iter().enumerate().for_each(|(i, response)| {
+                            responses.iter().zip(&available).for_each(|(response, &i)| {
                                 let selected = i == scene_player.highlighted_response();
-                                let button = response_button(ui, &response.text, selected);
+                                let text = FormattedLine::new(
+                                    &response.text,
+                                    &scene_manager,
+                                    player.name(),
+                                )
+                                .text;
+                                let button = response_button(ui, &text, selected);
                                 if button.clicked() {
                                     scene_player_input = Some(ScenePlayerInput::Select(i));
                                 } else if button.hovered() {
@@ -110,38 +129,99 @@ pub fn dialogue_ui(
                             };
                         }
                     })
+                });
+
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut typewriter_settings.auto_advance, "auto");
+                    ui.checkbox(&mut typewriter_settings.fast_forward, "skip seen");
+                    ui.toggle_value(&mut show_backlog.0, "backlog");
+                    ui.add(
+                        egui::Slider::new(
+                            &mut typewriter_settings.characters_per_second,
+                            5.0..=120.0,
+                        )
+                        .text("text speed"),
+                    );
                 })
             })
         });
 
     if let Some(input) = scene_player_input {
-        scene_player.input(
+        let scene = scene_player.scene().clone();
+        if let Some(response) = scene_player.input(
             input,
             &mut scene_manager,
             &mut end_scene_event,
             &mut scene_commands_event,
-        );
+        ) {
+            let response = FormattedLine::new(&response, &scene_manager, player.name());
+            history.record_response(&scene, &response.text);
+        }
     }
 }
 
+pub fn backlog_ui(
+    mut contexts: EguiContexts,
+    history: Res<DialogueHistory>,
+    mut show_backlog: ResMut<ShowBacklog>,
+) {
+    let ctx = contexts.ctx_mut();
+
+    Window::new("Backlog")
+        .open(&mut show_backlog.0)
+        .collapsible(false)
+        .anchor(Align2::CENTER_TOP, [0.0, 10.0])
+        .show(ctx, |ui| {
+            ScrollArea::vertical()
+                .max_height(300.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for entry in history.entries() {
+                        match entry {
+                            HistoryEntry::Line { speaker, text, .. } => {
+                                ui.horizontal_wrapped(|ui| {
+                                    ui.label(RichText::new(speaker).strong());
+                                    ui.label(text);
+                                });
+                            }
+                            HistoryEntry::Response { text, .. } => {
+                                ui.label(RichText::new(format!("> {text}")).color(Color32::YELLOW));
+                            }
+                        }
+                    }
+                });
+        });
+}
+
 pub fn dialogue_ui_input(
     keyboard_input: Res<ButtonInput<KeyCode>>,
     mut scene_manager: ResMut<SceneManager>,
     mut scene_player: Option<ResMut<ScenePlayer>>,
     mut end_scene_event: EventWriter<EndSceneEvent>,
     mut static_command_event: EventWriter<StaticCommandsEvent>,
+    mut typewriter: ResMut<Typewriter>,
+    mut history: ResMut<DialogueHistory>,
+    mut show_backlog: ResMut<ShowBacklog>,
+    player_query: Query<&RpgEntity, With<Player>>,
 ) {
     let Some(ref mut scene_player) = scene_player else {
         return;
     };
 
+    if keyboard_input.just_pressed(KeyCode::KeyB) {
+        show_backlog.0 = !show_backlog.0;
+    }
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        show_backlog.0 = false;
+    }
+
     if keyboard_input.just_pressed(KeyCode::KeyW) || keyboard_input.just_pressed(KeyCode::ArrowUp) {
         scene_player.input(
             ScenePlayerInput::MoveUp,
             &mut scene_manager,
             &mut end_scene_event,
             &mut static_command_event,
-        )
+        );
     }
     if keyboard_input.just_pressed(KeyCode::KeyS) || keyboard_input.just_pressed(KeyCode::ArrowDown)
     {
@@ -150,15 +230,28 @@ pub fn dialogue_ui_input(
             &mut scene_manager,
             &mut end_scene_event,
             &mut static_command_event,
-        )
+        );
     }
     if keyboard_input.just_pressed(KeyCode::KeyE) || keyboard_input.just_pressed(KeyCode::Enter) {
-        scene_player.input(
+        // the first press finishes typing out the line, the second advances
+        if !typewriter.is_complete(&scene_player.line_bookmark()) {
+            typewriter.complete();
+            return;
+        }
+        let scene = scene_player.scene().clone();
+        if let Some(response) = scene_player.input(
             ScenePlayerInput::SelectCurrent,
             &mut scene_manager,
             &mut end_scene_event,
             &mut static_command_event,
-        )
+        ) {
+            let player_name = player_query
+                .single()
+                .map(RpgEntity::name)
+                .unwrap_or_default();
+            let response = FormattedLine::new(&response, &scene_manager, player_name);
+            history.record_response(&scene, &response.text);
+        }
     }
 }
 
@@ -182,7 +275,7 @@ pub fn map_ui(
         .show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.image(SizedTexture::new(
-                    (&pixel_buffer_image_id).0,
+                    pixel_buffer_image_id.0,
                     egui::Vec2::new(28.0 * SCALE_FACTOR, 28.0 * SCALE_FACTOR),
                 ));
                 CollapsingHeader::new("Play Scene")
@@ -202,6 +295,86 @@ pub fn map_ui(
         });
 }
 
+/// Short messages shown in the corner of the screen, oldest first.
+#[derive(Resource, Debug, Default)]
+pub struct Toasts(Vec<(String, Timer)>);
+
+impl Toasts {
+    pub fn push(&mut self, message: impl Into<String>) {
+        self.0.push((
+            message.into(),
+            Timer::from_seconds(TOAST_DURATION, TimerMode::Once),
+        ));
+    }
+
+    pub fn messages(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|(message, _timer)| message.as_str())
+    }
+}
+
+/// Removes toasts that have been on screen long enough.
+pub fn update_toasts(mut toasts: ResMut<Toasts>, time: Res<Time>) {
+    toasts
+        .0
+        .retain_mut(|(_message, timer)| !timer.tick(time.delta()).finished());
+}
+
+pub fn toast_ui(mut contexts: EguiContexts, toasts: Res<Toasts>) {
+    if toasts.0.is_empty() {
+        return;
+    }
+    let ctx = contexts.ctx_mut();
+
+    egui::Area::new(egui::Id::new("toasts"))
+        .anchor(Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+        .show(ctx, |ui| {
+            for message in toasts.messages() {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(RichText::new(message).color(Color32::LIGHT_RED));
+                });
+            }
+        });
+}
+
+pub fn level_up_ui(
+    mut contexts: EguiContexts,
+    mut player_query: Query<&mut RpgEntity, With<Player>>,
+) {
+    let Ok(mut player) = player_query.single_mut() else {
+        return;
+    };
+    if player.unspent_points() == 0 {
+        return;
+    }
+    let ctx = contexts.ctx_mut();
+
+    let mut allocated_skill = None;
+    Window::new("Level Up!")
+        .collapsible(false)
+        .anchor(Align2::CENTER_TOP, [0.0, 10.0])
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "{} reached level {}! max hp: {}",
+                player.name(),
+                player.level(),
+                player.max_health()
+            ));
+            ui.label(format!("points to spend: {}", player.unspent_points()));
+            for skill in Skill::ALL {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{skill}: {}", player.stats().get(skill)));
+                    if ui.button("+").clicked() {
+                        allocated_skill = Some(skill);
+                    }
+                });
+            }
+        });
+
+    if let Some(skill) = allocated_skill {
+        player.allocate_point(skill);
+    }
+}
+
 pub fn battle_ui(
     mut contexts: EguiContexts,
     player_query: Query<Entity, With<Player>>,
@@ -261,12 +434,26 @@ pub fn debug_ui(
     entity_query: Query<(&Npc, &RpgEntity)>,
     scene_manager: Res<SceneManager>,
     item_manager: Res<ItemManager>,
+    load_diagnostics: Res<LoadDiagnostics>,
+    current_map: Option<Res<CurrentMap>>,
+    player_position: Option<Res<PlayerPosition>>,
 ) {
     let ctx = contexts.ctx_mut();
     let player = player_query.single().expect("player must exist.");
 
     Window::new("Debug Panel").show(ctx, |ui| {
         ScrollArea::vertical().show(ui, |ui| {
+            if !load_diagnostics.0.is_empty() {
+                CollapsingHeader::new(format!("Load Errors ({})", load_diagnostics.0.len()))
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        for diagnostic in &load_diagnostics.0 {
+                            ui.label(
+                                RichText::new(diagnostic.to_string()).color(Color32::LIGHT_RED),
+                            );
+                        }
+                    });
+            }
             CollapsingHeader::new("Player")
                 .default_open(true)
                 .show(ui, |ui| {
@@ -290,6 +477,14 @@ pub fn debug_ui(
             CollapsingHeader::new("Save Data")
                 .default_open(true)
                 .show(ui, |ui| {
+                    if let Some(current_map) = &current_map {
+                        match &player_position {
+                            Some(position) => {
+                                ui.label(format!("Map: {:?} at {:?}", current_map.0, position.0))
+                            }
+                            None => ui.label(format!("Map: {:?}", current_map.0)),
+                        };
+                    }
                     CollapsingHeader::new("Variables")
                         .default_open(true)
                         .show(ui, |ui| {
@@ -333,17 +528,36 @@ impl DebugUi for RpgEntity {
     fn show(&self, ui: &mut Ui) {
         ui.label(format!("name: {}", self.name()));
         ui.label(format!("hp: {}/{}", self.health(), self.max_health()));
+        ui.label(format!(
+            "level: {} ({} xp)",
+            self.level(),
+            self.experience()
+        ));
+        CollapsingHeader::new("Stats").show(ui, |ui| {
+            for skill in Skill::ALL {
+                ui.label(format!("{skill}: {}", self.stats().get(skill)));
+            }
+        });
         CollapsingHeader::new("Inventory").show(ui, |ui| {
             for item_instance in self.inventory.items.values() {
-                ui.label(format!(
-                    "{}{}",
-                    if self.is_equipped(&item_instance.instance_id()) {
-                        "[X] "
-                    } else {
-                        ""
-                    },
-                    item_instance.item_id()
-                ));
+                let mut label = String::new();
+                if self.is_equipped(&item_instance.instance_id()) {
+                    label.push_str("[X] ");
+                }
+                label.push_str(&item_instance.item_id().0);
+                if let Some(custom_name) = item_instance.custom_name() {
+                    label.push_str(&format!(" {custom_name:?}"));
+                }
+                if item_instance.quantity() > 1 {
+                    label.push_str(&format!(" x{}", item_instance.quantity()));
+                }
+                if let Some(durability) = item_instance.durability() {
+                    label.push_str(&format!(" (durability: {durability})"));
+                }
+                for enchantment in item_instance.enchantments() {
+                    label.push_str(&format!(" {enchantment:?}"));
+                }
+                ui.label(label);
             }
         });
     }
@@ -1,10 +1,26 @@
+use std::path::{Path, PathBuf};
+
 use bevy::prelude::{Commands, Query};
 
 use crate::{
-    AnyItem, Apparel, Character, Food, Inventory, ItemInstanceId, ItemManager, Npc, NpcId, Player,
-    Potion, RpgEntity, Shield, Weapon,
+    AnyItem, Apparel, Character, DataFormat, ExperienceCurve, Food, Inventory, ItemInstance,
+    ItemInstanceId, ItemManager, Npc, NpcId, Potion, RpgEntity, Shield, Weapon,
 };
 
+pub fn get_item_instance<'a>(
+    maybe_instance_id: Option<ItemInstanceId>,
+    inventory: &'a Inventory,
+    item_manager: &'a ItemManager,
+) -> Option<(&'a ItemInstance, &'a AnyItem)> {
+    maybe_instance_id
+        .and_then(|instance_id| inventory.get(&instance_id))
+        .and_then(|item_instance| {
+            item_manager
+                .get_item(item_instance.item_id())
+                .map(|item| (item_instance, item))
+        })
+}
+
 pub fn get_item<'a>(
     maybe_instance_id: Option<ItemInstanceId>,
     inventory: &Inventory,
@@ -57,36 +73,58 @@ pub fn get_shield<'a>(
 
 pub fn spawn_npc(
     commands: &mut Commands,
-    npc_query: Query<&Npc>,
+    npc_query: &Query<&Npc>,
     id: NpcId,
     character: Character,
+    curve: &ExperienceCurve,
 ) -> bool {
     if npc_query.into_iter().any(|npc| npc.id == id) {
         return false;
     }
+    let mut rpg_entity = RpgEntity::new(Some(character.name), curve);
+    if let Some(level) = character.level {
+        rpg_entity.set_level(level, curve);
+    }
+    rpg_entity.set_stats(character.stats);
     commands.spawn((
         Npc {
             id,
             image: character.image,
             voice: character.voice,
         },
-        RpgEntity::new(Some(character.name)),
+        rpg_entity,
     ));
     true
 }
 
-pub fn spawn_player(
-    commands: &mut Commands,
-    item_manager: &ItemManager,
-    name: &str,
-    items: &[&'static str],
-) {
-    let mut player = RpgEntity::new(Some(name.to_string()));
-    for item_id in items {
-        if let Some(item) = item_manager.spawn(item_id.to_string().into()) {
-            let instance_id = player.inventory.insert(item);
-            player.equip(instance_id);
+/// Every JSON, YAML and RON file under `path`, including in subfolders, sorted
+/// so they load in the same order every time.
+pub fn data_files<P: AsRef<Path>>(path: P) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in path.as_ref().read_dir()? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(data_files(&path)?);
+        } else if DataFormat::from_path(&path).is_ok() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The number of single character insertions, deletions and substitutions
+/// that turn `a` into `b`.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
         }
+        previous = current;
     }
-    commands.spawn((Player, player));
+    previous[b.len()]
 }
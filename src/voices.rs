@@ -0,0 +1,331 @@
+use std::{f32::consts::TAU, path::Path, time::Duration};
+
+use bevy::{
+    audio::{Decodable, Source, Volume},
+    platform::collections::HashMap,
+    prelude::*,
+};
+use rand_core::RngCore;
+use serde::Deserialize;
+
+use crate::{NpcVoice, Rng};
+
+const SAMPLE_RATE: u32 = 44_100;
+const BLIP_AMPLITUDE: f32 = 0.25;
+/// Blips longer than this, in seconds, would drown out the next character.
+const MAX_BLIP_DURATION: f32 = 1.0;
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Waveform {
+    #[default]
+    Square,
+    Sine,
+    Triangle,
+    #[serde(alias = "saw")]
+    Sawtooth,
+    Noise,
+}
+
+impl Waveform {
+    /// Samples the waveform at `phase`, which runs from 0 to 1 over one period.
+    fn sample(&self, phase: f32, noise: &mut u32) -> f32 {
+        match self {
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sine => (phase * TAU).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+            Waveform::Noise => {
+                // xorshift, so blips stay cheap and need no rng of their own
+                *noise ^= *noise << 13;
+                *noise ^= *noise >> 17;
+                *noise ^= *noise << 5;
+                *noise as f32 / u32::MAX as f32 * 2.0 - 1.0
+            }
+        }
+    }
+}
+
+#[derive(
+    Deserialize, Debug, Hash, Clone, PartialEq, Eq, derive_more::From, derive_more::Display,
+)]
+pub struct VoiceId(pub String);
+
+impl VoiceId {
+    pub fn new(s: &str) -> Self {
+        Self(s.to_string())
+    }
+}
+
+impl From<&NpcVoice> for VoiceId {
+    fn from(voice: &NpcVoice) -> Self {
+        Self(voice.0.clone())
+    }
+}
+
+/// A synthesized voice. Each spoken character plays one short blip at
+/// `pitch` hertz, shifted up or down by up to `variance` (as a fraction of
+/// the pitch).
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Voice {
+    id: VoiceId,
+    pitch: f32,
+    #[serde(default)]
+    variance: f32,
+    #[serde(default)]
+    waveform: Waveform,
+    /// Length of a blip in seconds.
+    duration: Option<f32>,
+    volume: Option<f32>,
+}
+
+impl Voice {
+    pub fn id(&self) -> &VoiceId {
+        &self.id
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.duration.unwrap_or(0.05)
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume.unwrap_or(1.0)
+    }
+
+    /// Checks the voice can be played, since voices are only data and a bad
+    /// value would otherwise surface as a crash mid-dialogue.
+    pub fn check(&self) -> anyhow::Result<()> {
+        let blip_samples = self.duration() * SAMPLE_RATE as f32;
+        if !(self.pitch.is_finite() && self.pitch > 0.0) {
+            anyhow::bail!("pitch has to be above 0 hertz, got {}", self.pitch);
+        }
+        if !(0.0..=1.0).contains(&self.variance) {
+            anyhow::bail!("variance has to be from 0 to 1, got {}", self.variance);
+        }
+        if !(blip_samples >= 1.0 && self.duration() <= MAX_BLIP_DURATION) {
+            anyhow::bail!(
+                "duration has to be above 0 and at most {MAX_BLIP_DURATION} seconds, got {}",
+                self.duration()
+            );
+        }
+        if !(self.volume().is_finite() && self.volume() >= 0.0) {
+            anyhow::bail!("volume can't be negative, got {}", self.volume());
+        }
+        Ok(())
+    }
+
+    pub fn blip<R: RngCore + ?Sized>(&self, rng: &mut R) -> Blip {
+        // uniform in -1..=1
+        let offset = rng.next_u32() as f32 / u32::MAX as f32 * 2.0 - 1.0;
+        Blip {
+            frequency: (self.pitch * (1.0 + self.variance * offset)).max(1.0),
+            waveform: self.waveform,
+            duration: self.duration(),
+            seed: rng.next_u32() | 1,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, Default)]
+pub struct VoiceManager {
+    voices: HashMap<VoiceId, Voice>,
+}
+
+impl VoiceManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load_voice<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<()> {
+        info!("loading voice: {:?}", path.as_ref());
+        let voice_json = std::fs::read_to_string(path)?;
+        let voice: Voice = serde_json::from_str(&voice_json)?;
+        voice
+            .check()
+            .map_err(|e| anyhow::anyhow!("voice {:?}: {e}", voice.id.0))?;
+        self.voices.insert(voice.id.clone(), voice);
+        Ok(())
+    }
+
+    pub fn with_load_voice<P: AsRef<Path>>(mut self, path: P) -> anyhow::Result<Self> {
+        self.load_voice(path)?;
+        Ok(self)
+    }
+
+    pub fn load_folder<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<()> {
+        path.as_ref()
+            .read_dir()?
+            .filter_map(Result::ok)
+            .filter(|entry| {
+                entry
+                    .path()
+                    .extension()
+                    .map(|ext| ext == "json")
+                    .unwrap_or(false)
+            })
+            .for_each(|entry| {
+                if let Err(e) = self.load_voice(entry.path()) {
+                    error!("failed to load voice: {e}");
+                }
+            });
+        Ok(())
+    }
+
+    pub fn with_load_folder<P: AsRef<Path>>(mut self, path: P) -> anyhow::Result<Self> {
+        self.load_folder(path)?;
+        Ok(self)
+    }
+
+    /// Returns the voice with `id`, falling back to the default voice.
+    pub fn get(&self, id: &VoiceId) -> Option<&Voice> {
+        self.voices
+            .get(id)
+            .or_else(|| self.voices.get(&VoiceId::from(&NpcVoice::default())))
+    }
+}
+
+/// A single procedurally generated speech blip.
+#[derive(Asset, TypePath, Debug, Clone, Copy, PartialEq)]
+pub struct Blip {
+    frequency: f32,
+    waveform: Waveform,
+    duration: f32,
+    seed: u32,
+}
+
+impl Decodable for Blip {
+    type DecoderItem = f32;
+    type Decoder = BlipDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        BlipDecoder {
+            blip: *self,
+            sample: 0,
+            total: (self.duration * SAMPLE_RATE as f32) as usize,
+            noise: self.seed,
+        }
+    }
+}
+
+pub struct BlipDecoder {
+    blip: Blip,
+    sample: usize,
+    total: usize,
+    noise: u32,
+}
+
+impl Iterator for BlipDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.sample >= self.total {
+            return None;
+        }
+        let time = self.sample as f32 / SAMPLE_RATE as f32;
+        let phase = (time * self.blip.frequency).fract();
+        // short attack and a linear decay so blips don't click
+        let progress = self.sample as f32 / self.total as f32;
+        let envelope = (progress * 20.0).min(1.0) * (1.0 - progress);
+        self.sample += 1;
+        Some(self.blip.waveform.sample(phase, &mut self.noise) * envelope * BLIP_AMPLITUDE)
+    }
+}
+
+impl Source for BlipDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.total - self.sample)
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Duration::try_from_secs_f32(self.blip.duration).ok()
+    }
+}
+
+#[derive(Event)]
+pub struct SpeechBlipEvent(pub VoiceId);
+
+impl SpeechBlipEvent {
+    pub fn handler(
+        mut commands: Commands,
+        mut speech_blip_events: EventReader<SpeechBlipEvent>,
+        voice_manager: Res<VoiceManager>,
+        mut blips: ResMut<Assets<Blip>>,
+        mut rng: Rng,
+    ) {
+        for SpeechBlipEvent(voice_id) in speech_blip_events.read() {
+            let Some(voice) = voice_manager.get(voice_id) else {
+                warn!("no voice with ID: {voice_id:?}");
+                continue;
+            };
+            commands.spawn((
+                AudioPlayer(blips.add(voice.blip(&mut **rng))),
+                PlaybackSettings::DESPAWN.with_volume(Volume::Linear(voice.volume())),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::audio::Decodable;
+    use bevy_rand::prelude::WyRand;
+    use rand_core::SeedableRng;
+
+    use crate::{Voice, VoiceId, VoiceManager};
+
+    fn voice_manager() -> VoiceManager {
+        VoiceManager::new()
+            .with_load_folder(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/voices"))
+            .unwrap()
+    }
+
+    #[test]
+    fn unknown_voices_fall_back_to_default() {
+        let voice_manager = voice_manager();
+        let voice = voice_manager.get(&VoiceId::new("nobody")).unwrap();
+        assert_eq!(voice.id(), &VoiceId::new("default"));
+    }
+
+    #[test]
+    fn blips_are_short_and_bounded() {
+        let voice_manager = voice_manager();
+        let mut rng = WyRand::seed_from_u64(0);
+        for id in ["default", "mario", "mike", "tabernacle", "jimmie", "jacob"] {
+            let voice = voice_manager.get(&VoiceId::new(id)).unwrap();
+            assert_eq!(voice.id(), &VoiceId::new(id));
+            let samples: Vec<f32> = voice.blip(&mut rng).decoder().collect();
+            assert!(!samples.is_empty());
+            assert!(samples.iter().all(|sample| sample.abs() <= 1.0));
+        }
+    }
+
+    #[test]
+    fn unplayable_voices_are_rejected() {
+        for voice in [
+            r#"{ "id": "silent", "pitch": 200, "duration": 0 }"#,
+            r#"{ "id": "backwards", "pitch": 200, "duration": -0.05 }"#,
+            r#"{ "id": "endless", "pitch": 200, "duration": 60 }"#,
+            r#"{ "id": "subsonic", "pitch": -1 }"#,
+            r#"{ "id": "wobbly", "pitch": 200, "variance": 2 }"#,
+            r#"{ "id": "loud", "pitch": 200, "volume": -1 }"#,
+        ] {
+            let voice: Voice = serde_json::from_str(voice).unwrap();
+            assert!(voice.check().is_err(), "{voice:?} was accepted");
+        }
+    }
+}